@@ -0,0 +1,422 @@
+use rand::Rng;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::mpsc;
+
+use network::{AbstractComponent, NetworkEvent, NodeIndex};
+use util::SimRng;
+
+// Plant and meat every agent starts with
+const START_STOCK: u32 = 10;
+
+// Units of its own good an agent produces per logic tick
+const PRODUCTION: u32 = 3;
+
+// Units of both goods an agent consumes per logic tick
+const CONSUMPTION: u32 = 1;
+
+// Most units exchanged over a single relation per logic tick
+const TRADE: u32 = 2;
+
+// Units of its own good an agent never trades away
+const RESERVE: u32 = 5;
+
+// Probability that an agent with an empty stock dies per logic tick
+const DEATH_RATE: f32 = 1.0;
+
+// Units of both goods an agent needs to have a child, 0 disables births
+const BIRTH: u32 = 0;
+
+// Neighbors of the parent a child is related to besides the parent
+const BIRTH_KIN: usize = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EconomyParams {
+    pub production: u32,
+    pub consumption: u32,
+    pub trade: u32,
+    pub reserve: u32,
+    pub death_rate: f32,
+    pub birth: u32,
+}
+
+impl EconomyParams {
+    pub fn new() -> EconomyParams {
+        EconomyParams {
+            production: PRODUCTION,
+            consumption: CONSUMPTION,
+            trade: TRADE,
+            reserve: RESERVE,
+            death_rate: DEATH_RATE,
+            birth: BIRTH,
+        }
+    }
+
+    // A single name=value pair, e.g. from the command line
+    pub fn set_pair(&mut self, pair: &str) -> Result<(), String> {
+        let mut split = pair.splitn(2, '=');
+
+        let name = split.next().unwrap().trim();
+        let value = match split.next() {
+            Some(v) => v.trim(),
+            None => return Err(format!("expected name = value, got {}", pair)),
+        };
+
+        let units = || value.parse::<u32>().map_err(|_| format!("{} is not a whole number", value));
+
+        match name {
+            "production" => self.production = units()?,
+            "consumption" => self.consumption = units()?,
+            "trade" => self.trade = units()?,
+            "reserve" => self.reserve = units()?,
+            "birth" => self.birth = units()?,
+            "death_rate" => {
+                let p = value.parse::<f32>().map_err(|_| format!("{} is not a number", value))?;
+                if !(p >= 0.0 && p <= 1.0) {
+                    return Err(String::from("death_rate must be between 0 and 1"));
+                }
+                self.death_rate = p;
+            }
+            _ => return Err(format!("unknown parameter {}", name)),
+        }
+
+        Ok(())
+    }
+
+    // Name and value of every parameter, e.g. for the results header
+    pub fn pairs(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("production", self.production.to_string()),
+            ("consumption", self.consumption.to_string()),
+            ("trade", self.trade.to_string()),
+            ("reserve", self.reserve.to_string()),
+            ("death_rate", self.death_rate.to_string()),
+            ("birth", self.birth.to_string()),
+        ]
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ProductionType {
+    Hunter,
+    Gatherer,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LogicComponent {
+    pub ptype: ProductionType,
+
+    pub plant: u32,
+    pub meat: u32,
+
+    pub alive: bool,
+
+    // Units of its own good offered to every partner this tick
+    pub offer: u32,
+}
+
+impl LogicComponent {
+    fn own_stock(&self) -> u32 {
+        match self.ptype {
+            ProductionType::Hunter => self.meat,
+            ProductionType::Gatherer => self.plant,
+        }
+    }
+
+    // Relations to agents of the other type are used for trade
+    fn trades_with(&self, other: &LogicComponent) -> bool {
+        self.ptype != other.ptype
+    }
+}
+
+impl AbstractComponent for LogicComponent {
+    fn new(rng: &mut SimRng) -> Self {
+        let p = rng.gen::<f32>();
+        let ptype = if p < 0.5 {
+            ProductionType::Hunter
+        } else {
+            ProductionType::Gatherer
+        };
+
+        LogicComponent { ptype: ptype, plant: START_STOCK, meat: START_STOCK, alive: true, offer: 0 }
+    }
+
+    fn group(&self) -> usize {
+        self.ptype as usize
+    }
+}
+
+// First part of an economy tick. Every agent splits the part of its own
+// good above the reserve evenly between its partners, so it never gives
+// away more than it has.
+pub fn offer(params: &EconomyParams, tx: &mpsc::Sender<NetworkEvent<LogicComponent>>, _: &mut SimRng,
+             me: (NodeIndex, &LogicComponent), neighbors: &[(NodeIndex, &LogicComponent)]) {
+    let partners = neighbors.iter().filter(|n| me.1.trades_with(n.1)).count() as u32;
+    let surplus = me.1.own_stock().saturating_sub(params.reserve);
+
+    let offer = if partners > 0 {
+        (surplus / partners).min(params.trade)
+    } else {
+        0
+    };
+
+    tx.send(NetworkEvent::UpdateAgent(me.0, LogicComponent { offer: offer, ..*me.1 })).unwrap();
+}
+
+// Second part of an economy tick. The hunter of every relation between a
+// hunter and a gatherer swaps the smaller of both offers one to one and
+// clears its offer afterwards, so a relation trades at most once per tick
+// even if the hunter runs again. The gatherer gives no more than it holds
+// once the exchange is applied and the hunter receives exactly that, so
+// goods are conserved under every update scheme.
+pub fn trade(_: &EconomyParams, tx: &mpsc::Sender<NetworkEvent<LogicComponent>>, _: &mut SimRng,
+             me: (NodeIndex, &LogicComponent), neighbors: &[(NodeIndex, &LogicComponent)]) {
+    if me.1.ptype != ProductionType::Hunter || me.1.offer == 0 {
+        return;
+    }
+
+    // Only this rule changes the stocks of the hunter during the pass
+    let mut meat = me.1.meat;
+
+    for n in neighbors.iter().filter(|n| me.1.trades_with(n.1)) {
+        let amount = me.1.offer.min(n.1.offer).min(meat);
+        if amount == 0 {
+            continue;
+        }
+        meat -= amount;
+
+        // Units the gatherer gave, 0 if it died in the meantime
+        let given = Rc::new(Cell::new(0));
+        let gatherer = given.clone();
+
+        tx.send(NetworkEvent::ModifyAgent(n.0, Box::new(move |g: &mut LogicComponent| {
+            let units = amount.min(g.plant);
+            g.plant -= units;
+            g.meat += units;
+            gatherer.set(units);
+        }))).unwrap();
+
+        tx.send(NetworkEvent::ModifyAgent(me.0, Box::new(move |h: &mut LogicComponent| {
+            let units = given.get();
+            h.meat -= units;
+            h.plant += units;
+        }))).unwrap();
+    }
+
+    tx.send(NetworkEvent::ModifyAgent(me.0, Box::new(|h: &mut LogicComponent| h.offer = 0))).unwrap();
+}
+
+// Last part of an economy tick. Agents produce and consume, agents with
+// an empty stock may die and agents with enough of both goods split them
+// with a child of their type.
+pub fn live(params: &EconomyParams, tx: &mpsc::Sender<NetworkEvent<LogicComponent>>, rng: &mut SimRng,
+            me: (NodeIndex, &LogicComponent), _: &[(NodeIndex, &LogicComponent)]) {
+    let mut next = *me.1;
+
+    match me.1.ptype {
+        ProductionType::Hunter => next.meat += params.production,
+        ProductionType::Gatherer => next.plant += params.production,
+    }
+
+    next.plant = next.plant.saturating_sub(params.consumption);
+    next.meat = next.meat.saturating_sub(params.consumption);
+
+    if (next.plant == 0 || next.meat == 0) && rng.gen::<f32>() < params.death_rate {
+        next.alive = false;
+    }
+
+    let child = if next.alive && params.birth > 0 && next.plant >= params.birth && next.meat >= params.birth {
+        let child = LogicComponent { plant: next.plant / 2, meat: next.meat / 2, offer: 0, ..next };
+        next.plant -= child.plant;
+        next.meat -= child.meat;
+
+        Some(child)
+    } else {
+        None
+    };
+
+    tx.send(NetworkEvent::UpdateAgent(me.0, next)).unwrap();
+    if !next.alive {
+        tx.send(NetworkEvent::RemoveAgent(me.0)).unwrap();
+    }
+    if let Some(child) = child {
+        tx.send(NetworkEvent::Birth(me.0, child, BIRTH_KIN)).unwrap();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use economy::*;
+    use graph::Graph;
+    use network::{Network, NetworkEvent, NodeIndex, Topology, UpdateScheme};
+    use util::seeded_rng;
+
+    fn agent(ptype: ProductionType, plant: u32, meat: u32) -> LogicComponent {
+        LogicComponent { ptype: ptype, plant: plant, meat: meat, alive: true, offer: 0 }
+    }
+
+    // Runs a rule for every agent of a star around agent 0 and applies
+    // the updates, children are appended as further leaves
+    fn step<F>(agents: &mut Vec<LogicComponent>, mut rule: F) -> Vec<usize>
+        where F: FnMut(&mpsc::Sender<NetworkEvent<LogicComponent>>, (NodeIndex, &LogicComponent), &[(NodeIndex, &LogicComponent)]) {
+        let (tx, rx) = mpsc::channel();
+
+        let mut graph = Graph::<(), ()>::new();
+        let nodes = agents.iter().map(|_| graph.add_node(())).collect::<Vec<_>>();
+        let idx = |i: usize| nodes[i];
+
+        for i in 0..agents.len() {
+            let neighbors = if i == 0 {
+                (1..agents.len()).map(|j| (idx(j), &agents[j])).collect::<Vec<_>>()
+            } else {
+                vec![(idx(0), &agents[0])]
+            };
+
+            rule(&tx, (idx(i), &agents[i]), &neighbors);
+        }
+
+        let mut removed = Vec::new();
+        let mut born = Vec::new();
+        for e in rx.try_iter() {
+            match e {
+                NetworkEvent::UpdateAgent(i, state) => agents[i.index()] = state,
+                NetworkEvent::ModifyAgent(i, f) => f(&mut agents[i.index()]),
+                NetworkEvent::RemoveAgent(i) => removed.push(i.index()),
+                NetworkEvent::Birth(_, child, _) => born.push(child),
+                _ => panic!("unexpected event"),
+            }
+        }
+
+        agents.extend(born);
+        removed
+    }
+
+    fn tick(params: &EconomyParams, agents: &mut Vec<LogicComponent>) -> Vec<usize> {
+        let mut rng = seeded_rng(1);
+        step(agents, |tx, me, n| offer(params, tx, &mut rng, me, n));
+        step(agents, |tx, me, n| trade(params, tx, &mut rng, me, n));
+        step(agents, |tx, me, n| live(params, tx, &mut rng, me, n))
+    }
+
+    #[test]
+    fn trade_conserves_goods() {
+        let mut params = EconomyParams::new();
+        params.production = 0;
+        params.consumption = 0;
+
+        // A hunter with plenty of meat trading with three gatherers
+        let mut agents = vec![
+            agent(ProductionType::Hunter, 10, 20),
+            agent(ProductionType::Gatherer, 8, 1),
+            agent(ProductionType::Gatherer, 30, 1),
+            agent(ProductionType::Gatherer, 5, 1),
+        ];
+        let total = |a: &Vec<LogicComponent>| (a.iter().map(|x| x.plant).sum::<u32>(), a.iter().map(|x| x.meat).sum::<u32>());
+        let before = total(&agents);
+
+        assert!(tick(&params, &mut agents).is_empty());
+        assert_eq!(total(&agents), before);
+
+        // 15 surplus meat split three ways but at most 2 per relation,
+        // the last gatherer has no surplus plant
+        assert_eq!((agents[0].plant, agents[0].meat), (14, 16));
+        assert_eq!((agents[1].plant, agents[1].meat), (6, 3));
+        assert_eq!((agents[3].plant, agents[3].meat), (5, 1));
+    }
+
+    #[test]
+    fn starvation() {
+        let params = EconomyParams::new();
+
+        // Without a partner a hunter lives off its plant stock
+        let mut agents = vec![agent(ProductionType::Hunter, 3, 10), agent(ProductionType::Hunter, 10, 10)];
+
+        assert!(tick(&params, &mut agents).is_empty());
+        assert!(tick(&params, &mut agents).is_empty());
+        assert_eq!(tick(&params, &mut agents), vec![0]);
+        assert!(!agents[0].alive);
+        assert_eq!(agents[1].meat, 10 + 3 * (PRODUCTION - CONSUMPTION));
+
+        let mut immortal = params;
+        immortal.death_rate = 0.0;
+        let mut agents = vec![agent(ProductionType::Gatherer, 10, 1)];
+        assert!(tick(&immortal, &mut agents).is_empty());
+        assert!(agents[0].alive && agents[0].meat == 0);
+    }
+
+    #[test]
+    fn births() {
+        let mut params = EconomyParams::new();
+        params.birth = 12;
+
+        let mut agents = vec![agent(ProductionType::Gatherer, 20, 13), agent(ProductionType::Gatherer, 10, 10)];
+        assert!(tick(&params, &mut agents).is_empty());
+
+        // 22 plant and 12 meat split between parent and child
+        assert_eq!(agents.len(), 3);
+        assert_eq!((agents[0].plant, agents[0].meat), (11, 6));
+        assert_eq!((agents[2].plant, agents[2].meat), (11, 6));
+        assert_eq!(agents[2].ptype, ProductionType::Gatherer);
+
+        // The other gatherer is below the threshold
+        assert_eq!((agents[1].plant, agents[1].meat), (12, 9));
+    }
+
+    // Total plant and meat of a network
+    fn totals(network: &Network<LogicComponent>) -> (u32, u32) {
+        network.nodes().iter().map(|&i| network.graph().node_payload(i).unwrap().logic())
+            .fold((0, 0), |(plant, meat), a| (plant + a.plant, meat + a.meat))
+    }
+
+    fn run(network: &mut Network<LogicComponent>, params: &EconomyParams, ticks: usize) {
+        for _ in 0..ticks {
+            network.logic_tick(|tx, rng, me, n| offer(params, tx, rng, me, n));
+            network.logic_tick(|tx, rng, me, n| trade(params, tx, rng, me, n));
+            network.logic_tick(|tx, rng, me, n| live(params, tx, rng, me, n));
+        }
+    }
+
+    // Agents that run several times or see partners that already traded
+    // neither create goods nor give away more than they have
+    #[test]
+    fn update_schemes() {
+        let mut closed = EconomyParams::new();
+        closed.production = 0;
+        closed.consumption = 0;
+
+        for &update in [UpdateScheme::Synchronous, UpdateScheme::RandomSequential, UpdateScheme::Continuous].iter() {
+            let mut network = Network::<LogicComponent>::generate(200, &Topology::WattsStrogatz { k: 4, beta: 0.1 }, 3);
+            network.set_update_scheme(update);
+
+            let stocks = |n: &Network<LogicComponent>| n.nodes().iter().map(|&i| n.graph().node_payload(i).unwrap().logic().plant).collect::<Vec<_>>();
+            let (before, plants) = (totals(&network), stocks(&network));
+
+            run(&mut network, &closed, 50);
+            assert_eq!(totals(&network), before, "{:?}", update);
+            assert!(stocks(&network) != plants);
+
+            // Trade alongside production, starvation and births
+            let mut params = EconomyParams::new();
+            params.birth = 20;
+            run(&mut network, &params, 50);
+        }
+    }
+
+    #[test]
+    fn params() {
+        let mut params = EconomyParams::new();
+
+        params.set_pair("trade = 4").unwrap();
+        params.set_pair("death_rate=0.5").unwrap();
+        assert_eq!(params.trade, 4);
+        assert_eq!(params.death_rate, 0.5);
+
+        assert!(params.set_pair("trade = 1.5").is_err());
+        assert!(params.set_pair("death_rate = 2").is_err());
+        assert!(params.set_pair("interest = 1").is_err());
+        assert_eq!(params.pairs().len(), 6);
+    }
+}
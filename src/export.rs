@@ -0,0 +1,462 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::io::prelude::*;
+use std::ops::Range;
+use std::path::Path;
+
+use util::Vec2;
+
+// Fraction of the image left empty around the network when fitting
+const FIT_MARGIN: f32 = 0.05;
+
+// Width of relations in pixels
+const LINE_WIDTH: f32 = 1.0;
+
+// Zlib stored blocks hold at most this many bytes
+const STORED_BLOCK: usize = 65535;
+
+// Anything circles and lines can be drawn on, the window or a file
+pub trait Canvas {
+    fn draw_circle(&mut self, pos: Vec2, r: f32, color: (f32, f32, f32));
+    fn draw_line(&mut self, p1: Vec2, p2: Vec2, color: (f32, f32, f32));
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Shape {
+    Circle(Vec2, f32, (f32, f32, f32)),
+    Line(Vec2, Vec2, (f32, f32, f32)),
+}
+
+// Shapes in the order they were drawn
+pub struct Drawing {
+    pub shapes: Vec<Shape>,
+}
+
+impl Drawing {
+    pub fn new() -> Drawing {
+        Drawing { shapes: Vec::new() }
+    }
+
+    // Smallest and largest corner of the box containing all shapes
+    fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        let mut points = Vec::new();
+
+        for shape in self.shapes.iter() {
+            match *shape {
+                Shape::Circle(pos, r, _) => {
+                    points.push(pos - Vec2::new(r, r));
+                    points.push(pos + Vec2::new(r, r));
+                }
+                Shape::Line(p1, p2, _) => {
+                    points.push(p1);
+                    points.push(p2);
+                }
+            }
+        }
+
+        let first = *points.first()?;
+        Some(points.iter().fold((first, first), |(lo, hi), p| {
+            (Vec2::new(lo.x().min(p.x()), lo.y().min(p.y())), Vec2::new(hi.x().max(p.x()), hi.y().max(p.y())))
+        }))
+    }
+}
+
+impl Canvas for Drawing {
+    fn draw_circle(&mut self, pos: Vec2, r: f32, color: (f32, f32, f32)) {
+        self.shapes.push(Shape::Circle(pos, r, color));
+    }
+
+    fn draw_line(&mut self, p1: Vec2, p2: Vec2, color: (f32, f32, f32)) {
+        self.shapes.push(Shape::Line(p1, p2, color));
+    }
+}
+
+// Part of the world shown in an image
+#[derive(Copy, Clone, Debug)]
+pub enum Framing {
+    // Same as the window, center and zoom as in the renderer
+    View { center: Vec2, zoom: f32 },
+    // Everything drawn, scaled to fill the image
+    Fit,
+}
+
+// Maps world coordinates to pixels, y points down in images
+struct Transform {
+    scale: f32,
+    offset: Vec2,
+}
+
+impl Transform {
+    fn new(drawing: &Drawing, size: (u32, u32), framing: Framing) -> Transform {
+        let (w, h) = (size.0 as f32, size.1 as f32);
+
+        // The renderer maps the height of the window to 2 / zoom world units
+        let (scale, center) = match framing {
+            Framing::View { center, zoom } => (zoom * h / 2.0, Vec2::new(0.0, 0.0) - center),
+            Framing::Fit => match drawing.bounds() {
+                Some((lo, hi)) => {
+                    let extent = hi - lo;
+                    let fill = 1.0 - 2.0 * FIT_MARGIN;
+                    let scale = (fill * w / extent.x().max(1e-6)).min(fill * h / extent.y().max(1e-6));
+
+                    (scale, (lo + hi).scale(0.5))
+                }
+                None => (1.0, Vec2::new(0.0, 0.0)),
+            },
+        };
+
+        Transform {
+            scale: scale,
+            offset: Vec2::new(w / 2.0 - center.x() * scale, h / 2.0 + center.y() * scale),
+        }
+    }
+
+    fn apply(&self, p: Vec2) -> (f32, f32) {
+        (self.offset.x() + p.x() * self.scale, self.offset.y() - p.y() * self.scale)
+    }
+}
+
+// Writes an SVG or PNG image depending on the extension of path
+pub fn write_file(path: &str, drawing: &Drawing, size: (u32, u32), framing: Framing) -> io::Result<()> {
+    if size.0 == 0 || size.1 == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: image size must not be zero", path)));
+    }
+
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+
+    let data = match extension.as_ref().map(|e| e.as_str()) {
+        Some("svg") => svg(drawing, size, framing).into_bytes(),
+        Some("png") => png(drawing, size, framing),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{}: expected .svg or .png", path))),
+    };
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(&data)?;
+    out.flush()
+}
+
+pub fn svg(drawing: &Drawing, size: (u32, u32), framing: Framing) -> String {
+    let transform = Transform::new(drawing, size, framing);
+    let (w, h) = size;
+
+    let mut out = String::new();
+    out.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n", w, h, w, h));
+    out.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    for shape in drawing.shapes.iter() {
+        match *shape {
+            Shape::Circle(pos, r, color) => {
+                let (x, y) = transform.apply(pos);
+                out.push_str(&format!("<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"/>\n", x, y, r * transform.scale, hex(color)));
+            }
+            Shape::Line(p1, p2, color) => {
+                let (x1, y1) = transform.apply(p1);
+                let (x2, y2) = transform.apply(p2);
+                out.push_str(&format!("<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                                      x1, y1, x2, y2, hex(color), LINE_WIDTH));
+            }
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+fn hex(color: (f32, f32, f32)) -> String {
+    let (r, g, b) = to_bytes(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn to_bytes(color: (f32, f32, f32)) -> (u8, u8, u8) {
+    let byte = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+    (byte(color.0), byte(color.1), byte(color.2))
+}
+
+// RGB pixels on a white background, top row first. Edges are
+// antialiased by the fraction of a pixel a shape covers.
+pub fn rasterize(drawing: &Drawing, size: (u32, u32), framing: Framing) -> Vec<u8> {
+    let transform = Transform::new(drawing, size, framing);
+    let (w, h) = (size.0 as i64, size.1 as i64);
+
+    let mut pixels = vec![255; (w * h * 3) as usize];
+
+    let mut blend = |x: i64, y: i64, color: (f32, f32, f32), coverage: f32| {
+        if x < 0 || y < 0 || x >= w || y >= h || coverage <= 0.0 {
+            return;
+        }
+
+        let i = ((y * w + x) * 3) as usize;
+        let coverage = coverage.min(1.0);
+        for (c, value) in [color.0, color.1, color.2].iter().enumerate() {
+            let old = pixels[i + c] as f32 / 255.0;
+            pixels[i + c] = ((old + (value - old) * coverage) * 255.0).round() as u8;
+        }
+    };
+
+    for shape in drawing.shapes.iter() {
+        match *shape {
+            Shape::Circle(pos, r, color) => {
+                let (cx, cy) = transform.apply(pos);
+                let r = r * transform.scale;
+
+                for y in span(cy - r - 1.0, cy + r + 1.0, h) {
+                    for x in span(cx - r - 1.0, cx + r + 1.0, w) {
+                        let d = ((x as f32 + 0.5 - cx).powi(2) + (y as f32 + 0.5 - cy).powi(2)).sqrt();
+                        blend(x, y, color, r - d + 0.5);
+                    }
+                }
+            }
+            Shape::Line(p1, p2, color) => {
+                let half = LINE_WIDTH / 2.0;
+                let reach = half + 1.0;
+
+                let (a, b) = match clip(transform.apply(p1), transform.apply(p2), (-reach, -reach), (w as f32 + reach, h as f32 + reach)) {
+                    Some(segment) => segment,
+                    None => continue,
+                };
+
+                for y in span(a.1.min(b.1) - reach, a.1.max(b.1) + reach, h) {
+                    // Part of the segment close enough to the row
+                    let (lo, hi) = if a.1 == b.1 {
+                        (0.0, 1.0)
+                    } else {
+                        let t1 = (y as f32 - reach - a.1) / (b.1 - a.1);
+                        let t2 = (y as f32 + 1.0 + reach - a.1) / (b.1 - a.1);
+                        (t1.min(t2).max(0.0), t1.max(t2).min(1.0))
+                    };
+                    let (x1, x2) = (a.0 + lo * (b.0 - a.0), a.0 + hi * (b.0 - a.0));
+
+                    for x in span(x1.min(x2) - reach, x1.max(x2) + reach, w) {
+                        let d = segment_distance((x as f32 + 0.5, y as f32 + 0.5), a, b);
+                        blend(x, y, color, half - d + 0.5);
+                    }
+                }
+            }
+        }
+    }
+
+    pixels
+}
+
+// Part of the segment from a to b inside the box from lo to hi
+fn clip(a: (f32, f32), b: (f32, f32), lo: (f32, f32), hi: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+
+    for &(d, from, to) in [(dx, lo.0 - a.0, hi.0 - a.0), (dy, lo.1 - a.1, hi.1 - a.1)].iter() {
+        if d == 0.0 {
+            if from > 0.0 || to < 0.0 {
+                return None;
+            }
+        } else {
+            let (u, v) = (from / d, to / d);
+            t0 = t0.max(u.min(v));
+            t1 = t1.min(u.max(v));
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+
+    Some(((a.0 + t0 * dx, a.1 + t0 * dy), (a.0 + t1 * dx, a.1 + t1 * dy)))
+}
+
+// Pixels from lo to hi that lie within 0..len
+fn span(lo: f32, hi: f32, len: i64) -> Range<i64> {
+    (lo.floor().max(0.0) as i64)..(hi.ceil().min(len as f32) as i64)
+}
+
+fn segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+
+    let t = if len2 > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len2).max(0.0).min(1.0)
+    } else {
+        0.0
+    };
+
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+// 8 bit RGB PNG of the rasterized drawing, the image data is
+// stored uncompressed
+pub fn png(drawing: &Drawing, size: (u32, u32), framing: Framing) -> Vec<u8> {
+    let pixels = rasterize(drawing, size, framing);
+    encode_png(&pixels, size)
+}
+
+fn encode_png(pixels: &[u8], size: (u32, u32)) -> Vec<u8> {
+    let (w, h) = size;
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&be(w));
+    header.extend_from_slice(&be(h));
+    // Bit depth, truecolor, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header);
+
+    // Every row starts with filter type 0
+    let row = (w * 3) as usize;
+    let mut raw = Vec::with_capacity((row + 1) * h as usize);
+    for line in pixels.chunks(row) {
+        raw.push(0);
+        raw.extend_from_slice(line);
+    }
+
+    chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut out, b"IEND", &[]);
+
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&be(data.len() as u32));
+
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&be(crc));
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32K window, no preset dictionary
+    let mut out = vec![0x78, 0x01];
+
+    let blocks = data.chunks(STORED_BLOCK).collect::<Vec<_>>();
+    if blocks.is_empty() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    for (i, block) in blocks.iter().enumerate() {
+        let last = if i + 1 == blocks.len() { 1 } else { 0 };
+        let len = block.len() as u16;
+
+        out.push(last);
+        out.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&be(adler32(data)));
+    out
+}
+
+fn be(x: u32) -> [u8; 4] {
+    [(x >> 24) as u8, (x >> 16) as u8, (x >> 8) as u8, x as u8]
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+
+#[cfg(test)]
+mod tests {
+    use export::*;
+    use super::{crc32, adler32, zlib_stored};
+    use util::Vec2;
+
+    fn scene() -> Drawing {
+        let mut drawing = Drawing::new();
+        drawing.draw_line(Vec2::new(-4.0, 0.0), Vec2::new(4.0, 0.0), (0.0, 0.0, 0.0));
+        drawing.draw_circle(Vec2::new(-4.0, 0.0), 1.0, (1.0, 0.0, 0.0));
+        drawing.draw_circle(Vec2::new(4.0, 0.0), 1.0, (0.0, 0.0, 1.0));
+        drawing
+    }
+
+    fn pixel(pixels: &[u8], w: u32, x: u32, y: u32) -> (u8, u8, u8) {
+        let i = ((y * w + x) * 3) as usize;
+        (pixels[i], pixels[i + 1], pixels[i + 2])
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        // Stored blocks are split at 64K
+        let data = vec![7; 70000];
+        let z = zlib_stored(&data);
+        assert_eq!(z.len(), 2 + 2 * 5 + data.len() + 4);
+        assert_eq!(z[2], 0);
+        assert_eq!(z[2 + 5 + 65535], 1);
+    }
+
+    #[test]
+    fn png_layout() {
+        let data = png(&scene(), (20, 10), Framing::Fit);
+
+        assert_eq!(&data[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&data[12..16], b"IHDR");
+        assert_eq!(&data[16..24], &[0, 0, 0, 20, 0, 0, 0, 10]);
+        assert_eq!(&data[data.len() - 8..data.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn fit() {
+        let (w, h) = (100, 51);
+        let pixels = rasterize(&scene(), (w, h), Framing::Fit);
+
+        // Both circles are inside the image, the corners stay empty
+        assert_eq!(pixel(&pixels, w, 10, 25), (255, 0, 0));
+        assert_eq!(pixel(&pixels, w, 89, 25), (0, 0, 255));
+        assert_eq!(pixel(&pixels, w, 50, 25), (0, 0, 0));
+        assert_eq!(pixel(&pixels, w, 0, 0), (255, 255, 255));
+        assert_eq!(pixel(&pixels, w, 50, 5), (255, 255, 255));
+    }
+
+    #[test]
+    fn view() {
+        // Zoomed in on the right circle
+        let framing = Framing::View { center: Vec2::new(-4.0, 0.0), zoom: 0.5 };
+        let pixels = rasterize(&scene(), (40, 40), framing);
+        assert_eq!(pixel(&pixels, 40, 20, 20), (0, 0, 255));
+
+        let svg = svg(&scene(), (40, 40), framing);
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains("cx=\"20.00\" cy=\"20.00\" r=\"10.00\" fill=\"#0000ff\""));
+    }
+
+    // Shapes far larger than the image only cost the pixels they cover
+    #[test]
+    fn clipping() {
+        let mut drawing = Drawing::new();
+        drawing.draw_circle(Vec2::new(0.0, 0.0), 1e6, (1.0, 0.0, 0.0));
+        drawing.draw_line(Vec2::new(-1e6, -1e6), Vec2::new(1e6, 1e6), (0.0, 0.0, 0.0));
+
+        let framing = Framing::View { center: Vec2::new(0.0, 0.0), zoom: 1.0 };
+        let pixels = rasterize(&drawing, (32, 32), framing);
+
+        // The diagonal over the circle filling the image, y points up
+        assert_eq!(pixel(&pixels, 32, 0, 0), (255, 0, 0));
+        for i in 0..32 {
+            assert_eq!(pixel(&pixels, 32, i, 31 - i).0, 0);
+        }
+
+        assert!(write_file("empty.png", &drawing, (0, 32), framing).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+// 6x10 fixed font of the xorg misc-misc collection, which is in the
+// public domain. Printable ASCII as a 96x60 image with one bit per pixel,
+// most significant bit first, 16 glyphs per row starting at ' '.
+const FONT: &'static [u8] = include_bytes!("font_6x10.raw");
+
+pub const GLYPH_W: u32 = 6;
+pub const GLYPH_H: u32 = 10;
+
+const COLUMNS: u32 = 16;
+
+pub const ATLAS_W: u32 = 96;
+pub const ATLAS_H: u32 = 60;
+
+const FIRST: u32 = 32;
+const LAST: u32 = 126;
+
+// The unused cell after '~' is filled, it is sampled to draw solid rectangles
+const SOLID: u32 = LAST + 1;
+
+// One byte per pixel, 255 where the font is set, top row first
+pub fn atlas() -> Vec<u8> {
+    let mut pixels = vec![0; (ATLAS_W * ATLAS_H) as usize];
+
+    for y in 0..ATLAS_H {
+        for x in 0..ATLAS_W {
+            let bit = y * ATLAS_W + x;
+            if FONT[(bit / 8) as usize] & (0x80 >> (bit % 8)) != 0 {
+                pixels[bit as usize] = 255;
+            }
+        }
+    }
+
+    let (sx, sy) = cell(SOLID);
+    for y in sy..sy + GLYPH_H {
+        for x in sx..sx + GLYPH_W {
+            pixels[(y * ATLAS_W + x) as usize] = 255;
+        }
+    }
+
+    pixels
+}
+
+// Top left corner of a code point's cell in the atlas
+fn cell(code: u32) -> (u32, u32) {
+    let i = code - FIRST;
+    ((i % COLUMNS) * GLYPH_W, (i / COLUMNS) * GLYPH_H)
+}
+
+// Cell of a character, characters outside the font are shown as '?'
+pub fn glyph(c: char) -> (u32, u32) {
+    let code = c as u32;
+
+    if code >= FIRST && code <= LAST {
+        cell(code)
+    } else {
+        cell('?' as u32)
+    }
+}
+
+pub fn solid() -> (u32, u32) {
+    cell(SOLID)
+}
+
+// Column and row of every visible character, lines are split at '\n'
+pub fn layout(text: &str) -> Vec<(u32, u32, char)> {
+    let mut glyphs = Vec::new();
+
+    for (row, line) in text.lines().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            if c != ' ' {
+                glyphs.push((col as u32, row as u32, c));
+            }
+        }
+    }
+
+    glyphs
+}
+
+// Width and height of a text in glyph cells
+pub fn extent(text: &str) -> (u32, u32) {
+    let width = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+    (width as u32, text.lines().count() as u32)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use font::*;
+
+    fn rows(pixels: &[u8], c: char) -> Vec<String> {
+        let (gx, gy) = glyph(c);
+
+        (gy..gy + GLYPH_H).map(|y| {
+            (gx..gx + GLYPH_W).map(|x| if pixels[(y * ATLAS_W + x) as usize] > 0 { '#' } else { '.' }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn glyphs() {
+        let pixels = atlas();
+        assert_eq!(pixels.len(), (ATLAS_W * ATLAS_H) as usize);
+
+        assert_eq!(rows(&pixels, 'A'), vec![
+            "......",
+            "..#...",
+            ".#.#..",
+            "#...#.",
+            "#...#.",
+            "#####.",
+            "#...#.",
+            "#...#.",
+            "......",
+            "......",
+        ]);
+
+        assert!(rows(&pixels, ' ').iter().all(|r| r == "......"));
+        assert_eq!(glyph('\u{e9}'), glyph('?'));
+
+        let (sx, sy) = solid();
+        assert!((0..GLYPH_H).all(|y| pixels[((sy + y) * ATLAS_W + sx) as usize] == 255));
+    }
+
+    #[test]
+    fn text_layout() {
+        assert_eq!(layout("a b\nc"), vec![(0, 0, 'a'), (2, 0, 'b'), (0, 1, 'c')]);
+        assert_eq!(extent("a b\nc"), (3, 2));
+        assert_eq!(extent(""), (0, 0));
+    }
+}
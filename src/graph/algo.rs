@@ -0,0 +1,437 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use graph::{Graph, NodeIndex};
+
+// Breadth first traversal starting at a single node
+// Yields nothing if the start node is stale
+pub struct Bfs<'a, T: 'a, S: 'a> {
+    graph: &'a Graph<T, S>,
+    queue: VecDeque<NodeIndex>,
+    visited: HashSet<NodeIndex>,
+}
+
+impl<'a, T: 'a, S: 'a> Bfs<'a, T, S> {
+    pub fn new(graph: &'a Graph<T, S>, start: NodeIndex) -> Bfs<'a, T, S> {
+        let mut queue = VecDeque::new();
+        let mut visited = HashSet::new();
+
+        if graph.contains_node(start) {
+            queue.push_back(start);
+            visited.insert(start);
+        }
+
+        Bfs { graph: graph, queue: queue, visited: visited }
+    }
+}
+
+impl<'a, T: 'a, S: 'a> Iterator for Bfs<'a, T, S> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = match self.queue.pop_front() {
+            Some(node) => node,
+            None => return None,
+        };
+
+        for (n, _) in self.graph.neighbors_iter(&node).unwrap() {
+            if self.visited.insert(n) {
+                self.queue.push_back(n);
+            }
+        }
+
+        Some(node)
+    }
+}
+
+// Depth first traversal in preorder starting at a single node
+// Yields nothing if the start node is stale
+pub struct Dfs<'a, T: 'a, S: 'a> {
+    graph: &'a Graph<T, S>,
+    stack: Vec<NodeIndex>,
+    visited: HashSet<NodeIndex>,
+}
+
+impl<'a, T: 'a, S: 'a> Dfs<'a, T, S> {
+    pub fn new(graph: &'a Graph<T, S>, start: NodeIndex) -> Dfs<'a, T, S> {
+        let mut stack = Vec::new();
+
+        if graph.contains_node(start) {
+            stack.push(start);
+        }
+
+        Dfs { graph: graph, stack: stack, visited: HashSet::new() }
+    }
+}
+
+impl<'a, T: 'a, S: 'a> Iterator for Dfs<'a, T, S> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            if !self.visited.insert(node) {
+                continue;
+            }
+
+            // Push in reverse so the first neighbor is explored first
+            let neighbors = self.graph.neighbors_iter(&node).unwrap().map(|(n, _)| n).collect::<Vec<_>>();
+            for n in neighbors.into_iter().rev() {
+                if !self.visited.contains(&n) {
+                    self.stack.push(n);
+                }
+            }
+
+            return Some(node);
+        }
+
+        None
+    }
+}
+
+// Number of hops from start to every reachable node
+pub fn hops<T, S>(graph: &Graph<T, S>, start: NodeIndex) -> HashMap<NodeIndex, usize> {
+    multi_source_hops(graph, &[start])
+}
+
+// Number of hops from every reachable node to the closest of the sources,
+// e.g. the distance of every agent to the nearest agent of some kind
+pub fn multi_source_hops<T, S>(graph: &Graph<T, S>, sources: &[NodeIndex]) -> HashMap<NodeIndex, usize> {
+    let mut dist = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    for &s in sources {
+        if graph.contains_node(s) && !dist.contains_key(&s) {
+            dist.insert(s, 0);
+            queue.push_back(s);
+        }
+    }
+
+    while let Some(node) = queue.pop_front() {
+        let d = dist[&node];
+
+        for (n, _) in graph.neighbors_iter(&node).unwrap() {
+            if !dist.contains_key(&n) {
+                dist.insert(n, d + 1);
+                queue.push_back(n);
+            }
+        }
+    }
+
+    dist
+}
+
+// Hop distances between all pairs of nodes, unreachable pairs are omitted
+pub fn all_pairs_hops<T, S>(graph: &Graph<T, S>) -> HashMap<NodeIndex, HashMap<NodeIndex, usize>> {
+    graph.node_indices().map(|n| (n, hops(graph, n))).collect()
+}
+
+// Connected components, ignoring edge direction in directed mode
+pub fn connected_components<T, S>(graph: &Graph<T, S>) -> Vec<Vec<NodeIndex>> {
+    let mut visited = HashSet::new();
+    let mut components = Vec::new();
+
+    for start in graph.node_indices() {
+        if !visited.insert(start) {
+            continue;
+        }
+
+        let mut component = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+
+        while let Some(node) = queue.pop_front() {
+            component.push(node);
+
+            let outgoing = graph.edges(node).unwrap();
+            let incoming = graph.incoming_edges(node).unwrap();
+
+            for &(n, _) in outgoing.iter().chain(incoming.iter()) {
+                if visited.insert(n) {
+                    queue.push_back(n);
+                }
+            }
+        }
+
+        components.push(component);
+    }
+
+    components
+}
+
+#[derive(Copy, Clone)]
+struct State {
+    cost: f32,
+    node: NodeIndex,
+}
+
+impl PartialEq for State {
+    fn eq(&self, other: &State) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for State {}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &State) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed so the BinaryHeap pops the cheapest state first
+impl Ord for State {
+    fn cmp(&self, other: &State) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+// Shortest path lengths from start to every reachable node
+// Weights are taken from the edge payload and must be non-negative
+pub fn dijkstra<T, S, F>(graph: &Graph<T, S>, start: NodeIndex, weight: F) -> HashMap<NodeIndex, f32>
+    where F: Fn(&S) -> f32 {
+    let mut dist = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    if !graph.contains_node(start) {
+        return dist;
+    }
+
+    dist.insert(start, 0.0);
+    heap.push(State { cost: 0.0, node: start });
+
+    while let Some(State { cost, node }) = heap.pop() {
+        // Outdated heap entry
+        if cost > dist[&node] {
+            continue;
+        }
+
+        for (n, payload) in graph.edges(node).unwrap() {
+            let next = cost + weight(payload);
+
+            let shorter = match dist.get(&n) {
+                Some(&d) => next < d,
+                None => true,
+            };
+
+            if shorter {
+                dist.insert(n, next);
+                heap.push(State { cost: next, node: n });
+            }
+        }
+    }
+
+    dist
+}
+
+// Shortest path from start to goal guided by an admissible heuristic
+// Returns the path length and the nodes along the path including both ends
+pub fn astar<T, S, F, H>(graph: &Graph<T, S>, start: NodeIndex, goal: NodeIndex, weight: F, heuristic: H) -> Option<(f32, Vec<NodeIndex>)>
+    where F: Fn(&S) -> f32, H: Fn(NodeIndex) -> f32 {
+    if !graph.contains_node(start) || !graph.contains_node(goal) {
+        return None;
+    }
+
+    let mut dist = HashMap::new();
+    let mut prev = HashMap::new();
+    let mut heap = BinaryHeap::new();
+
+    dist.insert(start, 0.0);
+    heap.push(State { cost: heuristic(start), node: start });
+
+    while let Some(State { cost, node }) = heap.pop() {
+        let d = dist[&node];
+
+        if node == goal {
+            let mut path = vec![goal];
+            let mut cur = goal;
+            while let Some(&p) = prev.get(&cur) {
+                path.push(p);
+                cur = p;
+            }
+            path.reverse();
+
+            return Some((d, path));
+        }
+
+        // Outdated heap entry
+        if cost > d + heuristic(node) {
+            continue;
+        }
+
+        for (n, payload) in graph.edges(node).unwrap() {
+            let next = d + weight(payload);
+
+            let shorter = match dist.get(&n) {
+                Some(&d) => next < d,
+                None => true,
+            };
+
+            if shorter {
+                dist.insert(n, next);
+                prev.insert(n, node);
+                heap.push(State { cost: next + heuristic(n), node: n });
+            }
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use graph::NodeIndex;
+    use graph::algo::*;
+
+    fn path(n: usize) -> (Graph<(), f32>, Vec<NodeIndex>) {
+        let mut g = Graph::new_undirected();
+
+        let nodes = (0..n).map(|_| g.add_node(())).collect::<Vec<_>>();
+        for i in 1..n {
+            g.add_edge(nodes[i-1], nodes[i], 1.0);
+        }
+
+        (g, nodes)
+    }
+
+    #[test]
+    fn bfs_order() {
+        let mut g = Graph::<(), ()>::new_undirected();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+        let d = g.add_node(());
+        g.add_node(());
+
+        g.add_edge(a, b, ());
+        g.add_edge(a, c, ());
+        g.add_edge(b, d, ());
+
+        let order = Bfs::new(&g, a).collect::<Vec<_>>();
+
+        assert_eq!(order.len(), 4);
+        assert_eq!(order[0], a);
+        assert_eq!(order[3], d);
+    }
+
+    #[test]
+    fn dfs_order() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+        let d = g.add_node(());
+
+        // Outgoing edges are reported most recent first
+        g.add_edge(a, c, ());
+        g.add_edge(a, b, ());
+        g.add_edge(b, d, ());
+        g.add_edge(d, a, ());
+
+        assert_eq!(Dfs::new(&g, a).collect::<Vec<_>>(), vec![a, b, d, c]);
+    }
+
+    #[test]
+    fn traversal_from_stale_node() {
+        let (mut g, nodes) = path(3);
+
+        g.remove_node(nodes[0]);
+
+        assert_eq!(Bfs::new(&g, nodes[0]).count(), 0);
+        assert_eq!(Dfs::new(&g, nodes[0]).count(), 0);
+        assert_eq!(Bfs::new(&g, nodes[1]).count(), 2);
+        assert!(hops(&g, nodes[0]).is_empty());
+    }
+
+    #[test]
+    fn hop_distances() {
+        let (g, nodes) = path(5);
+
+        let h = hops(&g, nodes[0]);
+        for i in 0..5 {
+            assert_eq!(h[&nodes[i]], i);
+        }
+
+        let h = multi_source_hops(&g, &[nodes[0], nodes[4]]);
+        assert_eq!(h[&nodes[1]], 1);
+        assert_eq!(h[&nodes[2]], 2);
+        assert_eq!(h[&nodes[3]], 1);
+
+        let all = all_pairs_hops(&g);
+        assert_eq!(all[&nodes[1]][&nodes[4]], 3);
+        assert_eq!(all[&nodes[4]][&nodes[1]], 3);
+    }
+
+    #[test]
+    fn components() {
+        let (mut g, nodes) = path(6);
+
+        g.remove_edge(nodes[2], nodes[3]);
+        g.remove_node(nodes[5]);
+        g.add_node(());
+
+        let mut sizes = connected_components(&g).iter().map(|c| c.len()).collect::<Vec<_>>();
+        sizes.sort();
+
+        assert_eq!(sizes, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn weak_components() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+
+        g.add_edge(b, a, ());
+        g.add_edge(b, c, ());
+
+        assert_eq!(connected_components(&g).len(), 1);
+    }
+
+    #[test]
+    fn dijkstra_weights() {
+        let mut g = Graph::<(), f32>::new_undirected();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+        let d = g.add_node(());
+
+        g.add_edge(a, b, 1.0);
+        g.add_edge(b, c, 1.0);
+        g.add_edge(a, c, 5.0);
+        g.add_edge(c, d, 0.5);
+
+        let dist = dijkstra(&g, a, |w| *w);
+
+        assert_eq!(dist[&a], 0.0);
+        assert_eq!(dist[&c], 2.0);
+        assert_eq!(dist[&d], 2.5);
+    }
+
+    #[test]
+    fn astar_path() {
+        let (mut g, nodes) = path(4);
+
+        // Shortcut that is longer than the path it skips
+        g.add_edge(nodes[0], nodes[3], 10.0);
+
+        let (cost, p) = astar(&g, nodes[0], nodes[3], |w| *w, |_| 0.0).unwrap();
+        assert_eq!(cost, 3.0);
+        assert_eq!(p, nodes);
+
+        g.remove_edge(nodes[1], nodes[2]);
+
+        let (cost, p) = astar(&g, nodes[0], nodes[2], |w| *w, |_| 0.0).unwrap();
+        assert_eq!(cost, 11.0);
+        assert_eq!(p, vec![nodes[0], nodes[3], nodes[2]]);
+
+        g.remove_edge(nodes[0], nodes[3]);
+        assert!(astar(&g, nodes[0], nodes[3], |w| *w, |_| 0.0).is_none());
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::{HashMap, VecDeque};
+
+use graph::{Graph, NodeIndex};
+use graph::algo::hops;
+
+// Degree divided by the largest possible degree n - 1
+// Outgoing degree in directed mode
+pub fn degree_centrality<T, S>(graph: &Graph<T, S>) -> HashMap<NodeIndex, f32> {
+    let n = graph.num_nodes();
+    let scale = if n > 1 { 1.0 / (n - 1) as f32 } else { 1.0 };
+
+    graph.node_indices()
+         .map(|i| (i, graph.edges(i).unwrap().len() as f32 * scale))
+         .collect()
+}
+
+// Brandes' algorithm on unweighted shortest paths
+// If normalized, values are divided by the number of pairs not involving the node
+pub fn betweenness_centrality<T, S>(graph: &Graph<T, S>, normalized: bool) -> HashMap<NodeIndex, f32> {
+    let mut centrality = graph.node_indices().map(|i| (i, 0.0)).collect::<HashMap<_, f32>>();
+
+    for s in graph.node_indices() {
+        // Nodes in order of non-decreasing distance from s
+        let mut stack = Vec::new();
+        let mut pred: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+        let mut sigma: HashMap<NodeIndex, f32> = HashMap::new();
+        let mut dist: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        sigma.insert(s, 1.0);
+        dist.insert(s, 0);
+        queue.push_back(s);
+
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+
+            let dv = dist[&v];
+            let sv = sigma[&v];
+
+            for (w, _) in graph.neighbors_iter(&v).unwrap() {
+                if !dist.contains_key(&w) {
+                    dist.insert(w, dv + 1);
+                    queue.push_back(w);
+                }
+
+                if dist[&w] == dv + 1 {
+                    *sigma.entry(w).or_insert(0.0) += sv;
+                    pred.entry(w).or_insert_with(Vec::new).push(v);
+                }
+            }
+        }
+
+        // Accumulate dependencies in reverse order of discovery
+        let mut delta: HashMap<NodeIndex, f32> = HashMap::new();
+        while let Some(w) = stack.pop() {
+            let dw = *delta.get(&w).unwrap_or(&0.0);
+
+            if let Some(ps) = pred.get(&w) {
+                for v in ps {
+                    *delta.entry(*v).or_insert(0.0) += sigma[v] / sigma[&w] * (1.0 + dw);
+                }
+            }
+
+            if w != s {
+                *centrality.get_mut(&w).unwrap() += dw;
+            }
+        }
+    }
+
+    let n = graph.num_nodes() as f32;
+
+    // Every pair was counted from both ends
+    let mut scale = if graph.is_directed() { 1.0 } else { 0.5 };
+
+    if normalized && n > 2.0 {
+        scale = 1.0 / ((n - 1.0) * (n - 2.0));
+    }
+
+    for c in centrality.values_mut() {
+        *c *= scale;
+    }
+
+    centrality
+}
+
+// Inverse average distance to all reachable nodes, scaled by the
+// fraction of reachable nodes so disconnected graphs are comparable
+pub fn closeness_centrality<T, S>(graph: &Graph<T, S>) -> HashMap<NodeIndex, f32> {
+    let n = graph.num_nodes();
+
+    graph.node_indices().map(|i| {
+        let dist = hops(graph, i);
+        let total = dist.values().sum::<usize>();
+        let reachable = dist.len() - 1;
+
+        let c = if total > 0 && n > 1 {
+            (reachable as f32 / total as f32) * (reachable as f32 / (n - 1) as f32)
+        } else {
+            0.0
+        };
+
+        (i, c)
+    }).collect()
+}
+
+// Power iteration on A + I, which has the same leading eigenvector as A
+// but does not oscillate on bipartite graphs. Normalized to unit length.
+// Uses incoming edges in directed mode.
+pub fn eigenvector_centrality<T, S>(graph: &Graph<T, S>, max_iter: usize, tol: f32) -> HashMap<NodeIndex, f32> {
+    let n = graph.num_nodes();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    // Sums run over nodes in index order so results are reproducible
+    let nodes = graph.node_indices().collect::<Vec<_>>();
+    let mut x = nodes.iter().map(|&i| (i, 1.0 / n as f32)).collect::<HashMap<_, f32>>();
+
+    for _ in 0..max_iter {
+        let mut next = x.clone();
+
+        for &i in nodes.iter() {
+            for &(j, _) in graph.incoming_edges(i).unwrap().iter() {
+                *next.get_mut(&i).unwrap() += x[&j];
+            }
+        }
+
+        let norm = nodes.iter().map(|i| next[i] * next[i]).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for v in next.values_mut() {
+                *v /= norm;
+            }
+        }
+
+        let err = nodes.iter().map(|i| (next[i] - x[i]).abs()).sum::<f32>();
+        x = next;
+
+        if err < n as f32 * tol {
+            break;
+        }
+    }
+
+    x
+}
+
+// PageRank with the given damping factor, values sum to one.
+// Rank of nodes without outgoing edges is spread over all nodes.
+pub fn pagerank<T, S>(graph: &Graph<T, S>, damping: f32, max_iter: usize, tol: f32) -> HashMap<NodeIndex, f32> {
+    let n = graph.num_nodes();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    let nodes = graph.node_indices().collect::<Vec<_>>();
+    let out = nodes.iter().map(|&i| graph.edges(i).unwrap()).collect::<Vec<_>>();
+
+    let mut x = nodes.iter().map(|&i| (i, 1.0 / n as f32)).collect::<HashMap<_, f32>>();
+
+    for _ in 0..max_iter {
+        let dangling = nodes.iter().zip(out.iter())
+                            .filter(|&(_, o)| o.is_empty())
+                            .map(|(i, _)| x[i])
+                            .sum::<f32>();
+
+        let base = (1.0 - damping) / n as f32 + damping * dangling / n as f32;
+        let mut next = nodes.iter().map(|&i| (i, base)).collect::<HashMap<_, f32>>();
+
+        for (i, o) in nodes.iter().zip(out.iter()) {
+            if o.is_empty() {
+                continue;
+            }
+
+            let share = damping * x[i] / o.len() as f32;
+            for &(j, _) in o.iter() {
+                *next.get_mut(&j).unwrap() += share;
+            }
+        }
+
+        let err = nodes.iter().map(|i| (next[i] - x[i]).abs()).sum::<f32>();
+        x = next;
+
+        if err < n as f32 * tol {
+            break;
+        }
+    }
+
+    x
+}
+
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use graph::NodeIndex;
+    use graph::centrality::*;
+
+    const EPS: f32 = 1e-4;
+
+    fn star(n: usize) -> (Graph<(), ()>, Vec<NodeIndex>) {
+        let mut g = Graph::new_undirected();
+
+        let nodes = (0..n).map(|_| g.add_node(())).collect::<Vec<_>>();
+        for i in 1..n {
+            g.add_edge(nodes[0], nodes[i], ());
+        }
+
+        (g, nodes)
+    }
+
+    fn path(n: usize) -> (Graph<(), ()>, Vec<NodeIndex>) {
+        let mut g = Graph::new_undirected();
+
+        let nodes = (0..n).map(|_| g.add_node(())).collect::<Vec<_>>();
+        for i in 1..n {
+            g.add_edge(nodes[i-1], nodes[i], ());
+        }
+
+        (g, nodes)
+    }
+
+    fn complete(n: usize) -> (Graph<(), ()>, Vec<NodeIndex>) {
+        let mut g = Graph::new_undirected();
+
+        let nodes = (0..n).map(|_| g.add_node(())).collect::<Vec<_>>();
+        for i in 0..n {
+            for j in i+1..n {
+                g.add_edge(nodes[i], nodes[j], ());
+            }
+        }
+
+        (g, nodes)
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < EPS
+    }
+
+    #[test]
+    fn degree() {
+        let (g, nodes) = star(5);
+        let c = degree_centrality(&g);
+
+        assert!(close(c[&nodes[0]], 1.0));
+        assert!(close(c[&nodes[1]], 0.25));
+    }
+
+    #[test]
+    fn betweenness_star() {
+        let (g, nodes) = star(5);
+
+        let c = betweenness_centrality(&g, false);
+        assert!(close(c[&nodes[0]], 6.0));
+        assert!(close(c[&nodes[3]], 0.0));
+
+        let c = betweenness_centrality(&g, true);
+        assert!(close(c[&nodes[0]], 1.0));
+    }
+
+    #[test]
+    fn betweenness_path() {
+        let (g, nodes) = path(5);
+        let c = betweenness_centrality(&g, false);
+
+        assert!(close(c[&nodes[0]], 0.0));
+        assert!(close(c[&nodes[1]], 3.0));
+        assert!(close(c[&nodes[2]], 4.0));
+        assert!(close(c[&nodes[3]], 3.0));
+    }
+
+    #[test]
+    fn betweenness_complete() {
+        let (g, nodes) = complete(5);
+        let c = betweenness_centrality(&g, true);
+
+        for n in nodes.iter() {
+            assert!(close(c[n], 0.0));
+        }
+    }
+
+    #[test]
+    fn closeness() {
+        let (g, nodes) = star(5);
+        let c = closeness_centrality(&g);
+
+        assert!(close(c[&nodes[0]], 1.0));
+        assert!(close(c[&nodes[1]], 4.0 / 7.0));
+
+        let (g, nodes) = path(3);
+        let c = closeness_centrality(&g);
+
+        assert!(close(c[&nodes[1]], 1.0));
+        assert!(close(c[&nodes[0]], 2.0 / 3.0));
+
+        let (g, nodes) = complete(4);
+        let c = closeness_centrality(&g);
+
+        for n in nodes.iter() {
+            assert!(close(c[n], 1.0));
+        }
+    }
+
+    #[test]
+    fn eigenvector() {
+        let (g, nodes) = complete(4);
+        let c = eigenvector_centrality(&g, 100, 1e-6);
+
+        for n in nodes.iter() {
+            assert!(close(c[n], 0.5));
+        }
+
+        // Leading eigenvector of a star is (sqrt(n-1), 1, ..., 1) normalized
+        let (g, nodes) = star(5);
+        let c = eigenvector_centrality(&g, 1000, 1e-7);
+
+        assert!(close(c[&nodes[0]], 0.5f32.sqrt()));
+        assert!(close(c[&nodes[1]], 0.125f32.sqrt()));
+    }
+
+    #[test]
+    fn pagerank_values() {
+        let (g, nodes) = complete(5);
+        let c = pagerank(&g, 0.85, 100, 1e-6);
+
+        for n in nodes.iter() {
+            assert!(close(c[n], 0.2));
+        }
+
+        let (g, nodes) = star(5);
+        let c = pagerank(&g, 0.85, 100, 1e-6);
+
+        assert!(close(c.values().sum::<f32>(), 1.0));
+        assert!(c[&nodes[0]] > c[&nodes[1]]);
+        assert!(close(c[&nodes[1]], c[&nodes[4]]));
+
+        let (g, nodes) = path(3);
+        let c = pagerank(&g, 0.85, 100, 1e-6);
+
+        assert!(c[&nodes[1]] > c[&nodes[0]]);
+        assert!(close(c[&nodes[0]], c[&nodes[2]]));
+    }
+
+    #[test]
+    fn pagerank_dangling() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        g.add_edge(a, b, ());
+
+        let c = pagerank(&g, 0.85, 100, 1e-6);
+
+        assert!(close(c[&a] + c[&b], 1.0));
+        assert!(c[&b] > c[&a]);
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rand::Rng;
+
+use graph::{Graph, NodeIndex};
+
+// Edge direction is ignored by everything in this module
+
+#[derive(Clone, Debug)]
+pub struct Partition {
+    // Community labels are numbered from 0
+    pub communities: HashMap<NodeIndex, usize>,
+    pub modularity: f32,
+}
+
+impl Partition {
+    fn new<T, S>(graph: &Graph<T, S>, communities: HashMap<NodeIndex, usize>) -> Partition {
+        let q = modularity(graph, &communities);
+
+        Partition { communities: communities, modularity: q }
+    }
+
+    pub fn num_communities(&self) -> usize {
+        self.communities.values().max().map_or(0, |c| c + 1)
+    }
+}
+
+// Newman modularity of an assignment of nodes to communities
+pub fn modularity<T, S>(graph: &Graph<T, S>, communities: &HashMap<NodeIndex, usize>) -> f32 {
+    let m = graph.num_edges() as f32;
+    if m == 0.0 {
+        return 0.0;
+    }
+
+    // Ordered so the sum below does not depend on hashing
+    let mut internal = BTreeMap::new();
+    let mut degree = BTreeMap::new();
+
+    for (a, b, _) in graph.edges_iter() {
+        let ca = communities[&a];
+        let cb = communities[&b];
+
+        if ca == cb {
+            *internal.entry(ca).or_insert(0.0) += 1.0;
+        }
+
+        *degree.entry(ca).or_insert(0.0) += 1.0;
+        *degree.entry(cb).or_insert(0.0) += 1.0;
+    }
+
+    degree.iter().map(|(c, d)| {
+        internal.get(c).unwrap_or(&0.0) / m - (d / (2.0 * m)) * (d / (2.0 * m))
+    }).sum()
+}
+
+// Weighted undirected graph on dense indices that Louvain
+// repeatedly coarsens
+struct Level {
+    adj: Vec<BTreeMap<usize, f32>>,
+    loops: Vec<f32>,
+}
+
+impl Level {
+    fn degree(&self, i: usize) -> f32 {
+        self.adj[i].values().sum::<f32>() + 2.0 * self.loops[i]
+    }
+
+    // Merge every community into a single node
+    fn aggregate(&self, comm: &[usize], num: usize) -> Level {
+        let mut adj = vec![BTreeMap::new(); num];
+        let mut loops = vec![0.0; num];
+
+        for i in 0..self.adj.len() {
+            let ci = comm[i];
+            loops[ci] += self.loops[i];
+
+            for (&j, &w) in self.adj[i].iter() {
+                let cj = comm[j];
+
+                if ci == cj {
+                    // Seen from both ends
+                    loops[ci] += w / 2.0;
+                } else {
+                    *adj[ci].entry(cj).or_insert(0.0) += w;
+                }
+            }
+        }
+
+        Level { adj: adj, loops: loops }
+    }
+
+    // Local moving phase, returns the renumbered community of every node
+    // and whether any node moved
+    fn local_moves<R: Rng>(&self, rng: &mut R) -> (Vec<usize>, usize, bool) {
+        let n = self.adj.len();
+        let degree = (0..n).map(|i| self.degree(i)).collect::<Vec<_>>();
+        let m2 = degree.iter().sum::<f32>();
+
+        let mut comm = (0..n).collect::<Vec<_>>();
+        let mut tot = degree.clone();
+        let mut improved = false;
+
+        let mut order = (0..n).collect::<Vec<_>>();
+
+        loop {
+            let mut moved = false;
+            rng.shuffle(&mut order);
+
+            for &i in order.iter() {
+                let old = comm[i];
+                let k = degree[i];
+
+                let mut links = BTreeMap::new();
+                for (&j, &w) in self.adj[i].iter() {
+                    *links.entry(comm[j]).or_insert(0.0) += w;
+                }
+
+                tot[old] -= k;
+
+                let mut best = old;
+                let mut best_gain = links.get(&old).unwrap_or(&0.0) - tot[old] * k / m2;
+
+                for (&c, &w) in links.iter() {
+                    let gain = w - tot[c] * k / m2;
+                    if gain > best_gain + 1e-6 {
+                        best = c;
+                        best_gain = gain;
+                    }
+                }
+
+                tot[best] += k;
+                comm[i] = best;
+
+                if best != old {
+                    moved = true;
+                    improved = true;
+                }
+            }
+
+            if !moved {
+                break;
+            }
+        }
+
+        let num = renumber(&mut comm);
+
+        (comm, num, improved)
+    }
+}
+
+// Map labels onto 0..k in order of first appearance, returns k
+fn renumber(labels: &mut [usize]) -> usize {
+    let mut map = HashMap::new();
+
+    for l in labels.iter_mut() {
+        let next = map.len();
+        *l = *map.entry(*l).or_insert(next);
+    }
+
+    map.len()
+}
+
+// Louvain modularity optimisation
+pub fn louvain<T, S, R: Rng>(graph: &Graph<T, S>, rng: &mut R) -> Partition {
+    let nodes = graph.node_indices().collect::<Vec<_>>();
+    let dense = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect::<HashMap<_, _>>();
+
+    let mut level = Level { adj: vec![BTreeMap::new(); nodes.len()], loops: vec![0.0; nodes.len()] };
+    for (a, b, _) in graph.edges_iter() {
+        let (i, j) = (dense[&a], dense[&b]);
+
+        if i == j {
+            level.loops[i] += 1.0;
+        } else {
+            *level.adj[i].entry(j).or_insert(0.0) += 1.0;
+            *level.adj[j].entry(i).or_insert(0.0) += 1.0;
+        }
+    }
+
+    // Community of every original node at the current level
+    let mut node_comm = (0..nodes.len()).collect::<Vec<_>>();
+
+    if graph.num_edges() > 0 {
+        loop {
+            let (comm, num, improved) = level.local_moves(rng);
+            if !improved {
+                break;
+            }
+
+            for c in node_comm.iter_mut() {
+                *c = comm[*c];
+            }
+
+            level = level.aggregate(&comm, num);
+        }
+    }
+
+    renumber(&mut node_comm);
+
+    Partition::new(graph, nodes.into_iter().zip(node_comm.into_iter()).collect())
+}
+
+// Asynchronous label propagation. Nodes adopt the most frequent label
+// among their neighbors in random order, ties are broken at random
+// unless the current label is among the most frequent ones.
+pub fn label_propagation<T, S, R: Rng>(graph: &Graph<T, S>, rng: &mut R, max_iter: usize) -> Partition {
+    let mut nodes = graph.node_indices().collect::<Vec<_>>();
+    let mut labels = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect::<HashMap<_, _>>();
+
+    for _ in 0..max_iter {
+        let mut changed = false;
+        rng.shuffle(&mut nodes);
+
+        for &n in nodes.iter() {
+            let mut counts = BTreeMap::new();
+
+            let outgoing = graph.edges(n).unwrap();
+            let incoming = if graph.is_directed() { graph.incoming_edges(n).unwrap() } else { Vec::new() };
+
+            for &(m, _) in outgoing.iter().chain(incoming.iter()) {
+                if m != n {
+                    *counts.entry(labels[&m]).or_insert(0) += 1;
+                }
+            }
+
+            let max = match counts.values().max() {
+                Some(&max) => max,
+                None => continue,
+            };
+
+            let current = labels[&n];
+            if counts.get(&current) == Some(&max) {
+                continue;
+            }
+
+            let candidates = counts.iter().filter(|&(_, &c)| c == max).map(|(&l, _)| l).collect::<Vec<_>>();
+            let label = candidates[rng.gen_range(0, candidates.len())];
+
+            labels.insert(n, label);
+            changed = true;
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let nodes = graph.node_indices().collect::<Vec<_>>();
+    let mut comm = nodes.iter().map(|n| labels[n]).collect::<Vec<_>>();
+    renumber(&mut comm);
+
+    Partition::new(graph, nodes.into_iter().zip(comm.into_iter()).collect())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use graph::Graph;
+    use graph::NodeIndex;
+    use graph::community::*;
+
+    // Cliques of the given size joined in a ring by single edges
+    fn ring_of_cliques(num: usize, size: usize) -> (Graph<(), ()>, Vec<Vec<NodeIndex>>) {
+        let mut g = Graph::new_undirected();
+
+        let cliques = (0..num).map(|_| {
+            let nodes = (0..size).map(|_| g.add_node(())).collect::<Vec<_>>();
+            for i in 0..size {
+                for j in i+1..size {
+                    g.add_edge(nodes[i], nodes[j], ());
+                }
+            }
+            nodes
+        }).collect::<Vec<_>>();
+
+        for c in 0..num {
+            g.add_edge(cliques[c][0], cliques[(c + 1) % num][1], ());
+        }
+
+        (g, cliques)
+    }
+
+    fn assert_recovers(p: &Partition, cliques: &[Vec<NodeIndex>]) {
+        assert_eq!(p.num_communities(), cliques.len());
+
+        for c in cliques {
+            for n in c {
+                assert_eq!(p.communities[n], p.communities[&c[0]]);
+            }
+        }
+    }
+
+    #[test]
+    fn modularity_two_cliques() {
+        let (g, cliques) = ring_of_cliques(2, 5);
+
+        let mut comm = HashMap::new();
+        for (i, c) in cliques.iter().enumerate() {
+            for n in c {
+                comm.insert(*n, i);
+            }
+        }
+
+        // Two edges between the cliques: m = 22, 10 internal edges
+        // and total degree 22 per clique
+        let q = modularity(&g, &comm);
+        assert!((q - 2.0 * (10.0 / 22.0 - 0.25)).abs() < 1e-5);
+
+        let all = g.node_indices().map(|n| (n, 0)).collect();
+        assert!(modularity(&g, &all).abs() < 1e-5);
+    }
+
+    #[test]
+    fn louvain_ring_of_cliques() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let (g, cliques) = ring_of_cliques(6, 5);
+
+        let p = louvain(&g, &mut rng);
+
+        assert_recovers(&p, &cliques);
+        assert!((p.modularity - modularity(&g, &p.communities)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn label_propagation_ring_of_cliques() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let (g, cliques) = ring_of_cliques(6, 5);
+
+        let p = label_propagation(&g, &mut rng, 100);
+
+        assert_recovers(&p, &cliques);
+        assert!(p.modularity > 0.6);
+    }
+
+    #[test]
+    fn isolated_nodes() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut g = Graph::<(), ()>::new_undirected();
+
+        for _ in 0..3 {
+            g.add_node(());
+        }
+
+        assert_eq!(louvain(&g, &mut rng).num_communities(), 3);
+        assert_eq!(label_propagation(&g, &mut rng, 10).num_communities(), 3);
+    }
+}
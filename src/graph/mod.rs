@@ -0,0 +1,1030 @@
+use std::cmp::max;
+
+pub mod algo;
+pub mod centrality;
+pub mod community;
+
+#[derive(Debug)]
+pub struct Graph<T, S> {
+    nodes: Vec<Node<T>>,
+    edges: Vec<Edge<S>>,
+
+    // Slots available for reuse
+    nodes_free: Vec<usize>,
+    edges_free: Vec<usize>,
+
+    // In undirected mode every edge record is visible from both
+    // of its endpoints
+    directed: bool,
+}
+
+// Handles carry the generation of the slot they were issued for.
+// Once a slot is freed its generation is bumped, so stale handles
+// are rejected instead of aliasing whatever reuses the slot.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeIndex {
+    index: usize,
+    generation: usize,
+}
+
+impl NodeIndex {
+    // Slot of the node, unique among live nodes only
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EdgeIndex {
+    index: usize,
+    generation: usize,
+}
+
+#[derive(Debug)]
+pub struct Node<T> {
+    free: bool,
+    generation: usize,
+
+    // Heads of the outgoing and incoming adjacency chains
+    first: Option<EdgeIndex>,
+    first_in: Option<EdgeIndex>,
+
+    payload: T,
+}
+
+impl<T> Node<T> {
+    fn new(payload: T, generation: usize) -> Node<T> {
+        Node { free: false, generation: generation, first: None, first_in: None, payload: payload }
+    }
+}
+
+#[derive(Debug)]
+pub struct Edge<S> {
+    free: bool,
+    generation: usize,
+    source: NodeIndex,
+    target: NodeIndex,
+
+    // Each edge is part of the outgoing chain of its source and the
+    // incoming chain of its target. Both chains are doubly linked so
+    // an edge can be unlinked without walking either of them.
+    next: Option<EdgeIndex>,
+    prev: Option<EdgeIndex>,
+    next_in: Option<EdgeIndex>,
+    prev_in: Option<EdgeIndex>,
+
+    payload: S,
+}
+
+pub struct NodeIterator<'a, T: 'a, S: 'a> {
+    graph: &'a Graph<T, S>,
+    cur_idx: usize,
+}
+
+impl<'a, T: 'a, S: 'a> Iterator for NodeIterator<'a, T, S> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cur_idx >= self.graph.nodes.len() {
+            return None;
+        }
+
+        if self.graph.nodes[self.cur_idx].free {
+            self.cur_idx += 1;
+            self.next()
+        } else {
+            let ref_t = Some(&self.graph.nodes[self.cur_idx].payload);
+            self.cur_idx += 1;
+            ref_t
+        }
+    }
+}
+
+pub struct NodeIndexIterator<'a, T: 'a> {
+    nodes: &'a [Node<T>],
+    cur_idx: usize,
+}
+
+impl<'a, T: 'a> Iterator for NodeIndexIterator<'a, T> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.cur_idx < self.nodes.len() {
+            let n = &self.nodes[self.cur_idx];
+            self.cur_idx += 1;
+
+            if !n.free {
+                return Some(NodeIndex { index: self.cur_idx - 1, generation: n.generation });
+            }
+        }
+
+        None
+    }
+}
+
+pub struct NeighborIterator<'a, T: 'a, S: 'a> {
+    graph: &'a Graph<T, S>,
+    cur_edge: Option<EdgeIndex>,
+
+    // Only walked in undirected mode, after the outgoing chain
+    cur_edge_in: Option<EdgeIndex>,
+}
+
+impl<'a, T: 'a, S: 'a> Iterator for NeighborIterator<'a, T, S> {
+    type Item = (NodeIndex, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(edge_idx) = self.cur_edge {
+            let e = &self.graph.edges[edge_idx.index];
+            let n = &self.graph.nodes[e.target.index];
+            self.cur_edge = e.next;
+
+            Some((e.target, &n.payload))
+        } else if let Some(edge_idx) = self.cur_edge_in {
+            let e = &self.graph.edges[edge_idx.index];
+            self.cur_edge_in = e.next_in;
+
+            // Self loops were already reported on the outgoing chain
+            if e.source == e.target {
+                return self.next();
+            }
+
+            let n = &self.graph.nodes[e.source.index];
+
+            Some((e.source, &n.payload))
+        } else {
+            None
+        }
+    }
+}
+
+pub struct EdgeIterator<'a, S: 'a> {
+    edges: &'a [Edge<S>],
+    cur_idx: usize,
+}
+
+impl<'a, S: 'a> Iterator for EdgeIterator<'a, S> {
+    type Item = (NodeIndex, NodeIndex, &'a S);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.cur_idx < self.edges.len() {
+            let e = &self.edges[self.cur_idx];
+            self.cur_idx += 1;
+
+            if !e.free {
+                return Some((e.source, e.target, &e.payload));
+            }
+        }
+
+        None
+    }
+}
+
+impl<S> Edge<S> {
+    fn new(source: NodeIndex, target: NodeIndex, payload: S, generation: usize) -> Edge<S> {
+        Edge { free: false, generation: generation, source: source, target: target,
+               next: None, prev: None, next_in: None, prev_in: None, payload: payload }
+    }
+}
+
+impl<T, S> Graph<T, S> {
+    pub fn new() -> Graph<T, S> {
+        Graph { nodes: Vec::new(), edges: Vec::new(),
+                nodes_free: Vec::new(), edges_free: Vec::new(),
+                directed: true }
+    }
+
+    pub fn new_undirected() -> Graph<T, S> {
+        Graph { directed: false, ..Graph::new() }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        max(self.nodes.len() - self.nodes_free.len(), 0)
+    }
+
+    pub fn num_edges(&self) -> usize {
+        max(self.edges.len() - self.edges_free.len(), 0)
+    }
+
+    pub fn contains_node(&self, node: NodeIndex) -> bool {
+        match self.nodes.get(node.index) {
+            Some(n) => !n.free && n.generation == node.generation,
+            None => false,
+        }
+    }
+
+    fn contains_edge_index(&self, edge: EdgeIndex) -> bool {
+        match self.edges.get(edge.index) {
+            Some(e) => !e.free && e.generation == edge.generation,
+            None => false,
+        }
+    }
+
+    pub fn nodes_iter<'a>(&'a self) -> NodeIterator<'a, T, S> {
+        NodeIterator { graph: self, cur_idx: 0 }
+    }
+
+    // Every edge record exactly once, also in undirected mode
+    pub fn edges_iter<'a>(&'a self) -> EdgeIterator<'a, S> {
+        EdgeIterator { edges: &self.edges, cur_idx: 0 }
+    }
+
+    pub fn node_indices<'a>(&'a self) -> NodeIndexIterator<'a, T> {
+        NodeIndexIterator { nodes: &self.nodes, cur_idx: 0 }
+    }
+
+    pub fn nodes_mut(&mut self) -> Vec<&mut T> {
+        self.nodes.iter_mut().filter(|x| !x.free).map(|x| &mut x.payload).collect()
+    }
+
+    pub fn neighbors_iter<'a>(&'a self, src: &NodeIndex) -> Option<NeighborIterator<'a, T, S>> {
+        if !self.contains_node(*src) {
+            return None;
+        }
+
+        let cur_edge_in = if self.directed {
+            None
+        } else {
+            self.nodes[src.index].first_in
+        };
+
+        Some(NeighborIterator { graph: self, cur_edge: self.nodes[src.index].first, cur_edge_in: cur_edge_in })
+    }
+
+    pub fn edges(&self, node: NodeIndex) -> Option<Vec<(NodeIndex, &S)>> {
+        if !self.contains_node(node) {
+            return None;
+        }
+
+        let mut ret = Vec::new();
+        let mut edge = self.nodes[node.index].first;
+
+        while let Some(edge_idx) = edge {
+            let e = &self.edges[edge_idx.index];
+            ret.push((e.target, &e.payload));
+
+            edge = e.next;
+        }
+
+        if !self.directed {
+            let mut edge = self.nodes[node.index].first_in;
+
+            while let Some(edge_idx) = edge {
+                let e = &self.edges[edge_idx.index];
+                if e.source != e.target {
+                    ret.push((e.source, &e.payload));
+                }
+
+                edge = e.next_in;
+            }
+        }
+
+        Some(ret)
+    }
+
+    // Same as edges in undirected mode
+    pub fn incoming_edges(&self, node: NodeIndex) -> Option<Vec<(NodeIndex, &S)>> {
+        if !self.directed {
+            return self.edges(node);
+        }
+
+        if !self.contains_node(node) {
+            return None;
+        }
+
+        let mut ret = Vec::new();
+        let mut edge = self.nodes[node.index].first_in;
+
+        while let Some(edge_idx) = edge {
+            let e = &self.edges[edge_idx.index];
+            ret.push((e.source, &e.payload));
+
+            edge = e.next_in;
+        }
+
+        Some(ret)
+    }
+
+
+    pub fn add_node(&mut self, payload: T) -> NodeIndex {
+        if let Some(index) = self.nodes_free.pop() {
+            // Generation was already bumped when the slot was freed
+            let generation = self.nodes[index].generation;
+            self.nodes[index] = Node::new(payload, generation);
+
+            NodeIndex { index: index, generation: generation }
+        } else {
+            let index = self.nodes.len();
+            self.nodes.push(Node::new(payload, 0));
+
+            NodeIndex { index: index, generation: 0 }
+        }
+    }
+
+    // Returns false if the handle is stale
+    // Runs in time proportional to the degree of the node
+    pub fn remove_node(&mut self, node_idx: NodeIndex) -> bool {
+        if !self.contains_node(node_idx) {
+            return false;
+        }
+
+        // Remove outgoing edges
+        while let Some(e_idx) = self.nodes[node_idx.index].first {
+            self.unlink_edge(e_idx);
+            self.free_edge(e_idx);
+        }
+
+        // Remove incoming edges
+        while let Some(e_idx) = self.nodes[node_idx.index].first_in {
+            self.unlink_edge(e_idx);
+            self.free_edge(e_idx);
+        }
+
+        self.nodes_free.push(node_idx.index);
+        self.nodes[node_idx.index].free = true;
+        self.nodes[node_idx.index].generation += 1;
+
+        true
+    }
+
+    // Returns None if either handle is stale
+    pub fn add_edge(&mut self, source: NodeIndex, target: NodeIndex, payload: S) -> Option<EdgeIndex> {
+        if !self.contains_node(source) || !self.contains_node(target) {
+            return None;
+        }
+
+        let edge_idx = if let Some(index) = self.edges_free.pop() {
+            let generation = self.edges[index].generation;
+            self.edges[index] = Edge::new(source, target, payload, generation);
+
+            EdgeIndex { index: index, generation: generation }
+        } else {
+            let index = self.edges.len();
+            self.edges.push(Edge::new(source, target, payload, 0));
+
+            EdgeIndex { index: index, generation: 0 }
+        };
+
+        self.link_edge(edge_idx);
+
+        Some(edge_idx)
+    }
+
+    // Returns false if no such edge exists
+    // In undirected mode the orientation of the arguments is irrelevant
+    pub fn remove_edge(&mut self, source: NodeIndex, target: NodeIndex) -> bool {
+        match self.find_edge(source, target) {
+            Some(e_idx) => {
+                self.unlink_edge(e_idx);
+                self.free_edge(e_idx);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Prepend edge to the outgoing chain of its source and the
+    // incoming chain of its target
+    fn link_edge(&mut self, edge_idx: EdgeIndex) {
+        let (source, target) = {
+            let e = &self.edges[edge_idx.index];
+            (e.source.index, e.target.index)
+        };
+
+        let first = self.nodes[source].first;
+        if let Some(f) = first {
+            self.edges[f.index].prev = Some(edge_idx);
+        }
+        self.edges[edge_idx.index].next = first;
+        self.nodes[source].first = Some(edge_idx);
+
+        let first_in = self.nodes[target].first_in;
+        if let Some(f) = first_in {
+            self.edges[f.index].prev_in = Some(edge_idx);
+        }
+        self.edges[edge_idx.index].next_in = first_in;
+        self.nodes[target].first_in = Some(edge_idx);
+    }
+
+    // Remove edge from both chains it is part of
+    fn unlink_edge(&mut self, edge_idx: EdgeIndex) {
+        let (source, target, next, prev, next_in, prev_in) = {
+            let e = &self.edges[edge_idx.index];
+            (e.source.index, e.target.index, e.next, e.prev, e.next_in, e.prev_in)
+        };
+
+        match prev {
+            None => self.nodes[source].first = next,
+            Some(p) => self.edges[p.index].next = next,
+        }
+        if let Some(n) = next {
+            self.edges[n.index].prev = prev;
+        }
+
+        match prev_in {
+            None => self.nodes[target].first_in = next_in,
+            Some(p) => self.edges[p.index].next_in = next_in,
+        }
+        if let Some(n) = next_in {
+            self.edges[n.index].prev_in = prev_in;
+        }
+    }
+
+    fn free_edge(&mut self, edge_idx: EdgeIndex) {
+        let e = &mut self.edges[edge_idx.index];
+        e.free = true;
+        e.generation += 1;
+
+        self.edges_free.push(edge_idx.index);
+    }
+
+    pub fn contains_edge(&self, source: NodeIndex, target: NodeIndex) -> bool {
+        self.edge_payload(source, target).is_some()
+    }
+
+    pub fn node_payload(&self, node: NodeIndex) -> Option<&T> {
+        if !self.contains_node(node) {
+            return None;
+        }
+
+        Some(&self.nodes[node.index].payload)
+    }
+
+    pub fn node_payload_mut(&mut self, node: NodeIndex) -> Option<&mut T> {
+        if !self.contains_node(node) {
+            return None;
+        }
+
+        Some(&mut self.nodes[node.index].payload)
+    }
+
+    fn find_edge(&self, source: NodeIndex, target: NodeIndex) -> Option<EdgeIndex> {
+        if !self.contains_node(source) || !self.contains_node(target) {
+            return None;
+        }
+
+        let mut edge = self.nodes[source.index].first;
+
+        while let Some(edge_idx) = edge {
+            if self.edges[edge_idx.index].target == target {
+                return Some(edge_idx);
+            }
+            edge = self.edges[edge_idx.index].next;
+        }
+
+        if !self.directed {
+            let mut edge = self.nodes[source.index].first_in;
+
+            while let Some(edge_idx) = edge {
+                if self.edges[edge_idx.index].source == target {
+                    return Some(edge_idx);
+                }
+                edge = self.edges[edge_idx.index].next_in;
+            }
+        }
+
+        None
+    }
+
+    pub fn edge_payload(&self, source: NodeIndex, target: NodeIndex) -> Option<&S> {
+        self.find_edge(source, target).map(move |e| &self.edges[e.index].payload)
+    }
+
+    pub fn edge_payload_mut(&mut self, source: NodeIndex, target: NodeIndex) -> Option<&mut S> {
+        match self.find_edge(source, target) {
+            Some(e) => Some(&mut self.edges[e.index].payload),
+            None => None,
+        }
+    }
+
+    pub fn edge_payload_by_index(&self, edge: EdgeIndex) -> Option<&S> {
+        if !self.contains_edge_index(edge) {
+            return None;
+        }
+
+        Some(&self.edges[edge.index].payload)
+    }
+
+    pub fn edge_payload_by_index_mut(&mut self, edge: EdgeIndex) -> Option<&mut S> {
+        if !self.contains_edge_index(edge) {
+            return None;
+        }
+
+        Some(&mut self.edges[edge.index].payload)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use std::time::Instant;
+
+    #[test]
+    fn construction() {
+        let mut g = Graph::<(),()>::new();
+
+        for _ in 0..10 {
+            let a = g.add_node(());
+            let b = g.add_node(());
+
+            g.add_edge(a, b, ());
+        }
+
+        assert_eq!(g.num_nodes(), 20);
+        assert_eq!(g.num_edges(), 10);
+    }
+
+    #[test]
+    fn remove_node() {
+        let mut g = Graph::<(),()>::new();
+
+        let mut a = g.add_node(());
+        for _ in 0..10 {
+            a = g.add_node(());
+            let b = g.add_node(());
+
+            g.add_edge(a, b, ());
+        }
+
+        g.remove_node(a);
+
+        assert_eq!(g.num_nodes(), 20);
+        assert_eq!(g.num_edges(), 9);
+    }
+
+    #[test]
+    fn remove_edge() {
+        let mut g = Graph::<(),()>::new();
+
+        let mut a = g.add_node(());
+        let mut b = g.add_node(());
+        for _ in 0..10 {
+            a = g.add_node(());
+            b = g.add_node(());
+
+            g.add_edge(a, b, ());
+        }
+
+        g.remove_edge(a, b);
+
+        assert_eq!(g.num_nodes(), 22);
+        assert_eq!(g.num_edges(), 9);
+    }
+
+    #[test]
+    fn remove_both() {
+        let mut g = Graph::<(),()>::new();
+
+        let mut a = g.add_node(());
+        let mut b = g.add_node(());
+        for _ in 0..10 {
+            a = g.add_node(());
+            b = g.add_node(());
+
+            g.add_edge(a, b, ());
+        }
+
+        assert_eq!(g.num_edges(), 10);
+
+        g.remove_edge(a, b);
+
+        assert_eq!(g.num_edges(), 9);
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+
+        g.add_edge(a, b, ());
+        g.add_edge(a, c, ());
+        g.add_edge(b, c, ());
+
+        assert_eq!(g.num_edges(), 12);
+
+        g.remove_node(a);
+
+        assert_eq!(g.num_nodes(), 24);
+        assert_eq!(g.num_edges(), 10);
+    }
+
+    #[test]
+    fn contains() {
+        let mut g = Graph::<(), ()>::new();
+
+        let mut vec = Vec::new();
+        for _ in 0..10 {
+            vec.push(g.add_node(()));
+        }
+
+        for i in 0..10 {
+            for k in 0..10 {
+                g.add_edge(vec[i], vec[k], ());
+           }
+        }
+
+        assert_eq!(g.num_edges(), 100);
+        assert_eq!(g.contains_edge(vec[5], vec[8]), true);
+        assert_eq!(g.contains_edge(vec[8], vec[5]), true);
+
+        g.remove_node(vec[5]);
+
+        assert_eq!(g.num_edges(), 81);
+        assert_eq!(g.contains_edge(vec[5], vec[8]), false);
+
+        // Incoming edges are removed as well
+        assert_eq!(g.contains_edge(vec[8], vec[5]), false);
+    }
+
+    #[test]
+    fn re_add_node() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        g.add_edge(a, b, ());
+
+        g.remove_node(b);
+
+        let c = g.add_node(());
+
+        assert_eq!(g.contains_edge(a, c), false);
+
+        // c reuses b's slot, but b must not alias it
+        assert_eq!(g.contains_node(b), false);
+        assert_eq!(g.contains_node(c), true);
+        assert!(b != c);
+    }
+
+    #[test]
+    fn stale_node_handle() {
+        let mut g = Graph::<i32, ()>::new();
+
+        let a = g.add_node(0);
+        let b = g.add_node(1);
+
+        g.remove_node(b);
+        let c = g.add_node(2);
+
+        assert_eq!(g.node_payload(b), None);
+        assert_eq!(g.node_payload_mut(b), None);
+        assert_eq!(g.node_payload(c), Some(&2));
+        assert!(g.edges(b).is_none());
+        assert!(g.neighbors_iter(&b).is_none());
+
+        assert_eq!(g.add_edge(a, b, ()), None);
+        assert_eq!(g.add_edge(b, a, ()), None);
+        assert_eq!(g.num_edges(), 0);
+
+        assert_eq!(g.remove_node(b), false);
+        assert_eq!(g.num_nodes(), 2);
+        assert_eq!(g.node_payload(c), Some(&2));
+    }
+
+    #[test]
+    fn stale_edge_handle() {
+        let mut g = Graph::<(), i32>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        let e = g.add_edge(a, b, 1).unwrap();
+        assert_eq!(g.edge_payload_by_index(e), Some(&1));
+
+        g.remove_edge(a, b);
+        let f = g.add_edge(b, a, 2).unwrap();
+
+        assert_eq!(g.edge_payload_by_index(e), None);
+        assert_eq!(g.edge_payload_by_index_mut(e), None);
+        assert_eq!(g.edge_payload_by_index(f), Some(&2));
+    }
+
+    #[test]
+    fn double_edge() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        g.add_edge(a, b, ());
+        g.add_edge(a, b, ());
+
+        assert_eq!(g.num_edges(), 2);
+
+        g.remove_edge(a, b);
+        
+        assert_eq!(g.num_edges(), 1);
+        assert_eq!(g.contains_edge(a, b), true);
+
+        g.add_edge(a, b, ());
+
+        g.remove_node(b);
+
+        assert_eq!(g.num_edges(), 0);
+    }
+
+    #[test]
+    fn delete_nonexisting_node() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        g.add_node(());
+
+        g.remove_node(a);
+        g.remove_node(a);
+
+        assert_eq!(g.num_nodes(), 1);
+        assert_eq!(g.num_edges(), 0);
+    }
+
+    #[test]
+    fn delete_nonexisting_edge() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        g.add_edge(a, b, ());
+
+        g.remove_edge(b, a);
+
+        assert_eq!(g.num_nodes(), 2);
+        assert_eq!(g.num_edges(), 1);
+    }
+
+    #[test]
+    fn large_total_graph() {
+        let mut g = Graph::<(), ()>::new();
+
+        let mut nodes = Vec::new();
+        for _ in 0..1e3 as usize {
+            nodes.push(g.add_node(()));
+        }
+
+        for i in 0..1e3 as usize {
+            for k in 0..1e3 as usize {
+                g.add_edge(nodes[i], nodes[k], ());
+            }
+        }
+
+        assert_eq!(g.num_nodes(), 1e3 as usize);
+        assert_eq!(g.num_edges(), 1e6 as usize);
+
+        g.remove_node(nodes[257]);
+
+        assert_eq!(g.num_nodes(), 1e3 as usize - 1);
+        assert_eq!(g.num_edges(), 999*999);
+    }
+
+    #[test]
+    fn actual_payload() {
+        let mut g = Graph::<i32, f32>::new();
+
+        let a = g.add_node(0);
+        let b = g.add_node(1);
+
+        g.add_edge(a, b, 0.123);
+
+        assert_eq!(g.node_payload(a), Some(&0));
+        assert_eq!(g.node_payload_mut(b), Some(&mut 1));
+        assert_eq!(g.edge_payload_mut(a, b), Some(&mut 0.123));
+        assert_eq!(g.edge_payload(b, a), None);
+    }
+
+    #[test]
+    fn nodes_iter() {
+        let mut g = Graph::<i32, ()>::new();
+
+        for i in 0..5 {
+            g.add_node(i);
+        }
+
+        assert_eq!(g.nodes_iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3, &4]);
+        assert_eq!(g.nodes_mut(), vec![&mut 0, &mut 1, &mut 2, &mut 3, &mut 4]);
+    }
+
+    #[test]
+    fn node_indices() {
+        let mut g = Graph::<i32, ()>::new();
+
+        let nodes = (0..5).map(|i| g.add_node(i)).collect::<Vec<_>>();
+
+        g.remove_node(nodes[1]);
+        g.remove_node(nodes[3]);
+
+        assert_eq!(g.node_indices().collect::<Vec<_>>(), vec![nodes[0], nodes[2], nodes[4]]);
+    }
+
+    #[test]
+    fn edges() {
+        let mut g = Graph::<(), i32>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+
+        g.add_edge(a, b, 23);
+        g.add_edge(a, c, 13);
+        g.add_edge(b, c, 31);
+
+        assert_eq!(g.edges(a), Some(vec![(c, &13), (b, &23)]));
+    }
+
+    #[test]
+    fn incoming_edges() {
+        let mut g = Graph::<(), i32>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+
+        g.add_edge(a, c, 13);
+        g.add_edge(b, c, 31);
+        g.add_edge(c, a, 7);
+
+        assert_eq!(g.incoming_edges(c), Some(vec![(b, &31), (a, &13)]));
+        assert_eq!(g.incoming_edges(a), Some(vec![(c, &7)]));
+
+        g.remove_node(b);
+
+        assert_eq!(g.incoming_edges(c), Some(vec![(a, &13)]));
+        assert_eq!(g.incoming_edges(b), None);
+    }
+
+    #[test]
+    fn self_loop() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        g.add_edge(a, a, ());
+        g.add_edge(a, b, ());
+        g.add_edge(b, a, ());
+
+        g.remove_edge(a, a);
+
+        assert_eq!(g.num_edges(), 2);
+        assert_eq!(g.contains_edge(a, b), true);
+
+        g.add_edge(a, a, ());
+        g.remove_node(a);
+
+        assert_eq!(g.num_edges(), 0);
+        assert_eq!(g.edges(b), Some(vec![]));
+        assert_eq!(g.incoming_edges(b), Some(vec![]));
+    }
+
+    #[test]
+    fn remove_middle_of_chain() {
+        let mut g = Graph::<(), ()>::new();
+
+        let a = g.add_node(());
+        let mut nodes = Vec::new();
+        for _ in 0..5 {
+            let n = g.add_node(());
+            g.add_edge(a, n, ());
+            nodes.push(n);
+        }
+
+        g.remove_node(nodes[2]);
+        g.remove_edge(a, nodes[0]);
+        g.remove_edge(a, nodes[4]);
+
+        assert_eq!(g.edges(a).unwrap().iter().map(|x| x.0).collect::<Vec<_>>(),
+                   vec![nodes[3], nodes[1]]);
+    }
+
+    // Removing every node of a long path must not scan the whole graph
+    // per removal, which would make this test quadratic
+    #[test]
+    fn large_sparse_removal() {
+        let mut g = Graph::<(), ()>::new();
+
+        let n = 1e5 as usize;
+        let mut nodes = Vec::new();
+        for _ in 0..n {
+            nodes.push(g.add_node(()));
+        }
+
+        for i in 1..n {
+            g.add_edge(nodes[i-1], nodes[i], ());
+            g.add_edge(nodes[i], nodes[i-1], ());
+        }
+
+        assert_eq!(g.num_edges(), 2*(n-1));
+
+        for i in (0..n).rev() {
+            g.remove_node(nodes[i]);
+        }
+
+        assert_eq!(g.num_nodes(), 0);
+        assert_eq!(g.num_edges(), 0);
+    }
+
+    // Run with `cargo test --release -- --ignored --nocapture`
+    // Time per removal should stay flat as the graph grows
+    #[test]
+    #[ignore]
+    fn bench_remove_node() {
+        for &n in [1e3 as usize, 1e4 as usize, 1e5 as usize, 1e6 as usize].iter() {
+            let mut g = Graph::<(), ()>::new();
+
+            let mut nodes = Vec::new();
+            for _ in 0..n {
+                nodes.push(g.add_node(()));
+            }
+
+            // Ring with constant degree
+            for i in 0..n {
+                g.add_edge(nodes[i], nodes[(i+1) % n], ());
+                g.add_edge(nodes[(i+1) % n], nodes[i], ());
+            }
+
+            let removals = 1000;
+            let start = Instant::now();
+            for i in 0..removals {
+                g.remove_node(nodes[i * (n / removals)]);
+            }
+            let elapsed = start.elapsed();
+            let ns = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+
+            println!("n = {:>8}: {:>8} ns per removal", n, ns / removals as u64);
+        }
+    }
+
+    #[test]
+    fn undirected() {
+        let mut g = Graph::<i32, i32>::new_undirected();
+
+        let a = g.add_node(0);
+        let b = g.add_node(1);
+        let c = g.add_node(2);
+
+        g.add_edge(a, b, 12);
+        g.add_edge(c, a, 23);
+
+        assert_eq!(g.is_directed(), false);
+        assert_eq!(g.num_edges(), 2);
+        assert_eq!(g.contains_edge(b, a), true);
+        assert_eq!(g.contains_edge(a, c), true);
+        assert_eq!(g.contains_edge(b, c), false);
+        assert_eq!(g.edge_payload(b, a), Some(&12));
+
+        assert_eq!(g.edges(a), Some(vec![(b, &12), (c, &23)]));
+        assert_eq!(g.edges(c), Some(vec![(a, &23)]));
+        assert_eq!(g.incoming_edges(b), Some(vec![(a, &12)]));
+        assert_eq!(g.neighbors_iter(&a).unwrap().collect::<Vec<_>>(), vec![(b, &1), (c, &2)]);
+        assert_eq!(g.neighbors_iter(&b).unwrap().collect::<Vec<_>>(), vec![(a, &0)]);
+        assert_eq!(g.edges_iter().count(), 2);
+
+        assert_eq!(g.remove_edge(b, a), true);
+        assert_eq!(g.num_edges(), 1);
+        assert_eq!(g.edges(b), Some(vec![]));
+
+        g.remove_node(a);
+
+        assert_eq!(g.num_edges(), 0);
+        assert_eq!(g.edges(c), Some(vec![]));
+    }
+
+    #[test]
+    fn undirected_self_loop() {
+        let mut g = Graph::<(), ()>::new_undirected();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+
+        g.add_edge(a, a, ());
+        g.add_edge(a, b, ());
+
+        assert_eq!(g.edges(a).unwrap().len(), 2);
+        assert_eq!(g.neighbors_iter(&a).unwrap().count(), 2);
+
+        g.remove_node(a);
+
+        assert_eq!(g.num_edges(), 0);
+    }
+
+    #[test]
+    fn edges_iter() {
+        let mut g = Graph::<(), i32>::new();
+
+        let a = g.add_node(());
+        let b = g.add_node(());
+        let c = g.add_node(());
+
+        g.add_edge(a, b, 1);
+        g.add_edge(b, c, 2);
+        g.add_edge(c, a, 3);
+
+        g.remove_edge(b, c);
+
+        assert_eq!(g.edges_iter().collect::<Vec<_>>(), vec![(a, b, &1), (c, a, &3)]);
+    }
+}
@@ -1,6 +1,4 @@
-use std::fs::File;
-use std::path::Path;
-use std::io::prelude::*;
+use std::time::{Duration, Instant};
 
 use util::Vec2;
 
@@ -9,16 +7,30 @@ use glium::backend::glutin::Display;
 use glium::glutin::EventsLoop;
 
 use input::InputEvent;
+use export::Canvas;
+use font;
+use shader::{self, Shader};
+
+// How often debug builds check the shader files for changes
+const RELOAD_PERIOD_MS: u64 = 500;
 
 
 pub struct Renderer {
     pub display: Display,
     pub event_loop: EventsLoop,
 
-    program: glium::Program,
+    circle_program: Shader,
+    line_program: Shader,
+    text_program: Shader,
+    last_reload: Instant,
+
+    font: glium::texture::Texture2d,
 
     circle_mesh: CircleMesh,
-    line_mesh: LineMesh,
+
+    // Circles and lines of the current frame, drawn with one call each
+    circles: Batch<CircleInstance>,
+    lines: Batch<LineVertex>,
 
     perspective_zoom: Option<[[f32; 4]; 4]>,
     perspective_shift: Option<[[f32; 4]; 4]>,
@@ -39,15 +51,22 @@ impl Renderer {
         let display = glium::Display::new(window, context, &event_loop).unwrap();
 
         let circle_mesh = CircleMesh::new(&display);
-        let line_mesh = LineMesh::new(&display);
-        let program = make_program(&display);
+        let circle_program = Shader::new(&display, shader::CIRCLE_VERT, shader::FRAG);
+        let line_program = Shader::new(&display, shader::LINE_VERT, shader::FRAG);
+        let text_program = Shader::new(&display, shader::TEXT_VERT, shader::TEXT_FRAG);
+        let font = make_font_texture(&display);
 
         Renderer {
             display: display,
             event_loop: event_loop,
-            program: program,
+            circle_program: circle_program,
+            line_program: line_program,
+            text_program: text_program,
+            last_reload: Instant::now(),
+            font: font,
             circle_mesh: circle_mesh,
-            line_mesh: line_mesh,
+            circles: Batch::new(),
+            lines: Batch::new(),
             perspective_zoom: None,
             perspective_shift: None,
             frame: None,
@@ -59,6 +78,14 @@ impl Renderer {
     pub fn begin_frame(&mut self) {
         assert!(self.frame.is_none());
 
+        if cfg!(debug_assertions) && self.last_reload.elapsed() >= Duration::from_millis(RELOAD_PERIOD_MS) {
+            self.last_reload = Instant::now();
+
+            self.circle_program.reload(&self.display);
+            self.line_program.reload(&self.display);
+            self.text_program.reload(&self.display);
+        }
+
         self.frame = Some(self.display.draw());
 
         self.perspective_zoom = Some(get_perspective_zoom(&mut self.frame.as_mut().unwrap(),
@@ -74,52 +101,143 @@ impl Renderer {
         self.frame.as_mut().unwrap().clear_color(r, g, b, 0.0);
     }
 
-    pub fn draw_circle(&mut self, pos: Vec2, r: f32, color: (f32, f32, f32)) {
+    fn flush(&mut self) {
+        let frame = self.frame.as_mut().unwrap();
+
+        let uniforms = uniform!{
+            perspective_zoom: self.perspective_zoom.unwrap(),
+            perspective_shift: self.perspective_shift.unwrap(),
+        };
+
+        if !self.lines.data.is_empty() {
+            let lines = self.lines.upload(&self.display);
+
+            frame.draw(lines,
+                       &glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+                       &self.line_program.program,
+                       &uniforms,
+                       &Default::default())
+                 .unwrap();
+        }
+
+        if !self.circles.data.is_empty() {
+            let circles = self.circles.upload(&self.display);
+
+            frame.draw((&self.circle_mesh.vertices, circles.per_instance().unwrap()),
+                       &self.circle_mesh.indices,
+                       &self.circle_program.program,
+                       &uniforms,
+                       &Default::default())
+                 .unwrap();
+        }
+    }
+
+    // Text with its top left corner at pos in window pixels, size is
+    // the height of a line in pixels. Lines are split at '\n'.
+    pub fn draw_text(&mut self, pos: (f32, f32), text: &str, color: (f32, f32, f32), size: f32) {
         assert!(self.frame.is_some());
 
-        let model = get_model_circle(pos, r);
-        self.frame
-            .as_mut()
-            .unwrap()
-            .draw(&self.circle_mesh.vertices,
-                  &self.circle_mesh.indices,
-                  &self.program,
-                  &uniform!{
-                      model: model,
-                      perspective_zoom: self.perspective_zoom.unwrap(),
-                      perspective_shift: self.perspective_shift.unwrap(),
-                      col: color
-                  },
-                  &Default::default())
-            .unwrap();
+        let scale = size / font::GLYPH_H as f32;
+        let (w, h) = (font::GLYPH_W as f32 * scale, font::GLYPH_H as f32 * scale);
+
+        let mut data = Vec::new();
+        for (col, row, c) in font::layout(text) {
+            let x = (pos.0 + col as f32 * w).round();
+            let y = (pos.1 + row as f32 * h).round();
+
+            push_quad(&mut data, (x, y), (w, h), font::glyph(c), (font::GLYPH_W, font::GLYPH_H));
+        }
+
+        self.draw_screen_quads(&data, color, 1.0);
     }
 
-    pub fn draw_line(&mut self, p1: Vec2, p2: Vec2, color: (f32, f32, f32)) {
+    // Size of a text drawn with draw_text in pixels
+    pub fn text_extent(&self, text: &str, size: f32) -> (f32, f32) {
+        let (cols, rows) = font::extent(text);
+        let scale = size / font::GLYPH_H as f32;
+
+        (cols as f32 * font::GLYPH_W as f32 * scale, rows as f32 * size)
+    }
+
+    // Filled rectangle with its top left corner at pos in window pixels
+    pub fn fill_rect(&mut self, pos: (f32, f32), size: (f32, f32), color: (f32, f32, f32), alpha: f32) {
         assert!(self.frame.is_some());
 
-        let model = get_model_line(p1, p2);
-        self.frame
-            .as_mut()
-            .unwrap()
-            .draw(&self.line_mesh.vertices,
-                  &self.line_mesh.indices,
-                  &self.program,
-                  &uniform!{
-                      model: model,
-                      perspective_zoom: self.perspective_zoom.unwrap(),
-                      perspective_shift: self.perspective_shift.unwrap(),
-                      col: color
-                  },
-                  &Default::default())
-            .unwrap();
+        // Sample the middle of the solid cell only
+        let (sx, sy) = font::solid();
+
+        let mut data = Vec::new();
+        push_quad(&mut data, pos, size, (sx + 1, sy + 1), (1, 1));
+
+        self.draw_screen_quads(&data, color, alpha);
+    }
+
+    fn draw_screen_quads(&mut self, data: &[TextVertex], color: (f32, f32, f32), alpha: f32) {
+        if data.is_empty() {
+            return;
+        }
+
+        self.flush();
+
+        let vertices = glium::VertexBuffer::new(&self.display, data).unwrap();
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+
+        let frame = self.frame.as_mut().unwrap();
+        let (width, height) = frame.get_dimensions();
+
+        let sampler = glium::uniforms::Sampler::new(&self.font)
+            .magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+            .minify_filter(glium::uniforms::MinifySamplerFilter::Nearest);
+
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            .. Default::default()
+        };
+
+        frame.draw(&vertices,
+                   &indices,
+                   &self.text_program.program,
+                   &uniform!{
+                       screen: [width as f32, height as f32],
+                       atlas: sampler,
+                       col: color,
+                       alpha: alpha
+                   },
+                   &params)
+             .unwrap();
     }
 
     pub fn end_frame(&mut self) {
         assert!(self.frame.is_some());
 
+        self.flush();
+
         self.frame.take().unwrap().finish().unwrap();
     }
 
+    // Center and zoom of the view, as used by export::Framing::View
+    pub fn view(&self) -> (Vec2, f32) {
+        (self.view_center, self.zoom)
+    }
+
+    // Width and height of the window in pixels
+    pub fn screen_size(&self) -> (f32, f32) {
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        (width as f32, height as f32)
+    }
+
+    // Inverse of the zoom and shift applied in the vertex shader,
+    // pos is in pixels from the top left corner of the window
+    pub fn screen_to_world(&self, pos: (f64, f64)) -> Vec2 {
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        let ar = height as f32 / width as f32;
+
+        let x = 2.0 * pos.0 as f32 / width as f32 - 1.0;
+        let y = 1.0 - 2.0 * pos.1 as f32 / height as f32;
+
+        Vec2::new(x / (self.zoom * ar), y / self.zoom) - self.view_center
+    }
+
     pub fn apply_events(&mut self, events: &[InputEvent]) {
         assert!(self.frame.is_none());
         for e in events {
@@ -140,12 +258,109 @@ impl Renderer {
     }
 }
 
+impl Canvas for Renderer {
+    // Circles and lines are queued and drawn in end_frame, or before
+    // anything that has to end up on top of them
+    fn draw_circle(&mut self, pos: Vec2, r: f32, color: (f32, f32, f32)) {
+        assert!(self.frame.is_some());
+
+        self.circles.data.push(CircleInstance {
+            center: [pos.x(), pos.y()],
+            radius: r,
+            color: [color.0, color.1, color.2],
+        });
+    }
+
+    fn draw_line(&mut self, p1: Vec2, p2: Vec2, color: (f32, f32, f32)) {
+        assert!(self.frame.is_some());
+
+        // Lines are drawn before circles, so queued circles go first
+        // to stay below this line
+        if !self.circles.data.is_empty() {
+            self.flush();
+        }
+
+        let color = [color.0, color.1, color.2];
+        self.lines.data.push(LineVertex { position: [p1.x(), p1.y()], color: color });
+        self.lines.data.push(LineVertex { position: [p2.x(), p2.y()], color: color });
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Vertex {
     pub position: [f32; 2],
 }
 implement_vertex!(Vertex, position);
 
+#[derive(Copy, Clone)]
+struct CircleInstance {
+    center: [f32; 2],
+    radius: f32,
+    color: [f32; 3],
+}
+implement_vertex!(CircleInstance, center, radius, color);
+
+#[derive(Copy, Clone)]
+struct LineVertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+implement_vertex!(LineVertex, position, color);
+
+// Vertices collected over a frame and uploaded at once. The buffer
+// only grows, so it is reused by later frames.
+struct Batch<T: Copy + glium::Vertex> {
+    data: Vec<T>,
+    buffer: Option<glium::VertexBuffer<T>>,
+}
+
+impl<T: Copy + glium::Vertex> Batch<T> {
+    fn new() -> Self {
+        Batch { data: Vec::new(), buffer: None }
+    }
+
+    fn upload(&mut self, display: &Display) -> glium::vertex::VertexBufferSlice<T> {
+        let fits = self.buffer.as_ref().map_or(false, |b| b.len() >= self.data.len());
+        if !fits {
+            let capacity = self.data.len().next_power_of_two();
+            self.buffer = Some(glium::VertexBuffer::empty_dynamic(display, capacity).unwrap());
+        }
+
+        let slice = self.buffer.as_ref().unwrap().slice(0..self.data.len()).unwrap();
+        slice.write(&self.data);
+        self.data.clear();
+
+        slice
+    }
+}
+
+// Vertex of text and overlays, position in window pixels
+#[derive(Copy, Clone)]
+struct TextVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+implement_vertex!(TextVertex, position, tex_coords);
+
+// Two triangles covering a rectangle on screen, textured with
+// the given rectangle of the font atlas in atlas pixels
+fn push_quad(data: &mut Vec<TextVertex>, pos: (f32, f32), size: (f32, f32), cell: (u32, u32), cell_size: (u32, u32)) {
+    let (x0, y0) = pos;
+    let (x1, y1) = (pos.0 + size.0, pos.1 + size.1);
+
+    let u0 = cell.0 as f32 / font::ATLAS_W as f32;
+    let v0 = cell.1 as f32 / font::ATLAS_H as f32;
+    let u1 = (cell.0 + cell_size.0) as f32 / font::ATLAS_W as f32;
+    let v1 = (cell.1 + cell_size.1) as f32 / font::ATLAS_H as f32;
+
+    let corners = [(x0, y0, u0, v0), (x1, y0, u1, v0), (x1, y1, u1, v1),
+                   (x0, y0, u0, v0), (x1, y1, u1, v1), (x0, y1, u0, v1)];
+
+    for &(x, y, u, v) in corners.iter() {
+        data.push(TextVertex { position: [x, y], tex_coords: [u, v] });
+    }
+}
+
 struct CircleMesh {
     vertices: glium::VertexBuffer<Vertex>,
     indices: glium::index::NoIndices,
@@ -185,27 +400,6 @@ impl CircleMesh {
     }
 }
 
-struct LineMesh {
-    vertices: glium::VertexBuffer<Vertex>,
-    indices: glium::index::NoIndices,
-}
-
-impl LineMesh {
-    fn new(display: &Display) -> Self {
-        let p1 = Vertex { position: [0.0, 0.0] };
-        let p2 = Vertex { position: [0.0, 1.0] };
-
-        let data = vec![p1, p2];
-
-        let vertex_buffer = glium::VertexBuffer::new(display, &data).unwrap();
-
-        LineMesh {
-            vertices: vertex_buffer,
-            indices: glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
-        }
-    }
-}
-
 fn get_perspective_zoom(frame: &glium::Frame, zoom: f32) -> [[f32; 4]; 4] {
     let perspective = {
         let (width, height) = frame.get_dimensions();
@@ -241,45 +435,11 @@ fn get_perspective_shift(frame: &glium::Frame,
     perspective
 }
 
-fn get_model_circle(pos: Vec2, r: f32) -> [[f32; 4]; 4] {
-    let model = {
-        [
-            [r, 0.0, 0.0, 0.0],
-            [0.0, r, 0.0, 0.0],
-            [0.0, 0.0, 0.0, 0.0],
-            [pos.x(), pos.y(), 0.0, 1.0]
-        ]
-    };
-
-    model
-}
-
-fn get_model_line(p1: Vec2, p2: Vec2) -> [[f32; 4]; 4] {
-    let model = {
-        let d = p2 - p1;
-
-        [
-            [0.0, 0.0, 0.0, 0.0],
-            [d.x(), d.y(), 0.0, 0.0],
-            [0.0, 0.0, 0.0, 0.0],
-            [p1.x(), p1.y(), 0.0, 1.0]
-        ]
-    };
-
-    model
-}
-
-fn make_program(display: &Display) -> glium::Program {
-    let vertex_shader_src = load_shader("src/shader/vert_shader.glslv");
-    let fragment_shader_src = load_shader("src/shader/frag_shader.glslf");
-
-    glium::Program::from_source(display, &vertex_shader_src, &fragment_shader_src, None).unwrap()
-}
+// White texture with the font in the alpha channel. The first row of
+// the atlas is uploaded first, so atlas rows map directly to v.
+fn make_font_texture(display: &Display) -> glium::texture::Texture2d {
+    let pixels = font::atlas().into_iter().flat_map(|a| vec![255, 255, 255, a]).collect::<Vec<u8>>();
+    let image = glium::texture::RawImage2d::from_raw_rgba(pixels, (font::ATLAS_W, font::ATLAS_H));
 
-fn load_shader(file: &str) -> String {
-    let path = Path::new(file);
-    let mut file = File::open(&path).unwrap();
-    let mut s = String::new();
-    file.read_to_string(&mut s).unwrap();
-    s
+    glium::texture::Texture2d::new(display, image).unwrap()
 }
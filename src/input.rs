@@ -9,6 +9,12 @@ pub struct InputHandler {
     keyset: HashSet<VK>,
     mouseset: [bool; 2],
 
+    // Clicks not yet turned into events
+    pressed: bool,
+    released: bool,
+    right_clicked: bool,
+    moved: bool,
+
     zoom: f32,
 
     mouse_pos: (f64, f64),
@@ -16,15 +22,38 @@ pub struct InputHandler {
 
     mouse_scroll_sensitivity: f32,
     mouse_move_sensitivity: f32,
+
+    // Factor a physics parameter changes by per key press
+    param_step: f32,
 }
 
 #[derive(Debug)]
 pub enum InputEvent {
     Quit,
     ToggleFreeze,
+    // Single logic tick, e.g. while paused
+    Step,
+    ScaleSpeed(f32),
+    ToggleCommunities,
+    ToggleHud,
+    Export,
+    CycleIntegrator,
+    CycleUpdateScheme,
+    CycleLayout,
+    NextParam,
+    ScaleParam(f32),
     Rebuild,
     Shift(f32, f32),
     Zoom(f32),
+    // Left button pressed, moved while held and released,
+    // positions are in window pixels
+    Press(f64, f64),
+    Drag(f64, f64),
+    Release(f64, f64),
+    // Right click in window pixels
+    TogglePin(f64, f64),
+    // Cursor moved to a position in window pixels
+    Hover(f64, f64),
 }
 
 impl InputHandler {
@@ -32,11 +61,16 @@ impl InputHandler {
         InputHandler {
             keyset: HashSet::new(),
             mouseset: [false; 2],
+            pressed: false,
+            released: false,
+            right_clicked: false,
+            moved: false,
             zoom: 0.0,
             mouse_pos: (0.0, 0.0),
             mouse_pos_last_pressed: (0.0, 0.0),
             mouse_scroll_sensitivity: 0.2,
             mouse_move_sensitivity: 0.0026,
+            param_step: 1.1,
         }
     }
 
@@ -74,6 +108,7 @@ impl InputHandler {
 
     fn mouse_moved_input(&mut self, x: f64, y: f64) {
         self.mouse_pos = (x, y);
+        self.moved = true;
     }
 
     fn mouse_click_input(&mut self, state: glutin::ElementState, button: glutin::MouseButton) {
@@ -83,12 +118,17 @@ impl InputHandler {
         match (state, button) {
             (ES::Pressed, MB::Left) => {
                 self.mouseset[0] = true;
+                self.pressed = true;
                 self.mouse_pos_last_pressed = self.mouse_pos;
             }
             (ES::Released, MB::Left) => {
                 self.mouseset[0] = false;
+                self.released = true;
+            }
+            (ES::Pressed, MB::Right) => {
+                self.mouseset[1] = true;
+                self.right_clicked = true;
             }
-            (ES::Pressed, MB::Right) => self.mouseset[1] = true,
             (ES::Released, MB::Right) => self.mouseset[1] = false,
 
             _ => (),
@@ -116,11 +156,88 @@ impl InputHandler {
             self.keyset.remove(&VK::Space);
         }
 
+        if self.keyset.contains(&VK::N) {
+            events.push(InputEvent::Step);
+            self.keyset.remove(&VK::N);
+        }
+
+        if self.keyset.contains(&VK::LBracket) {
+            events.push(InputEvent::ScaleSpeed(0.5));
+            self.keyset.remove(&VK::LBracket);
+        }
+
+        if self.keyset.contains(&VK::RBracket) {
+            events.push(InputEvent::ScaleSpeed(2.0));
+            self.keyset.remove(&VK::RBracket);
+        }
+
+        if self.keyset.contains(&VK::C) {
+            events.push(InputEvent::ToggleCommunities);
+            self.keyset.remove(&VK::C);
+        }
+
+        if self.keyset.contains(&VK::H) {
+            events.push(InputEvent::ToggleHud);
+            self.keyset.remove(&VK::H);
+        }
+
+        if self.keyset.contains(&VK::E) {
+            events.push(InputEvent::Export);
+            self.keyset.remove(&VK::E);
+        }
+
+        if self.keyset.contains(&VK::I) {
+            events.push(InputEvent::CycleIntegrator);
+            self.keyset.remove(&VK::I);
+        }
+
+        if self.keyset.contains(&VK::U) {
+            events.push(InputEvent::CycleUpdateScheme);
+            self.keyset.remove(&VK::U);
+        }
+
+        if self.keyset.contains(&VK::L) {
+            events.push(InputEvent::CycleLayout);
+            self.keyset.remove(&VK::L);
+        }
+
+        if self.keyset.contains(&VK::P) {
+            events.push(InputEvent::NextParam);
+            self.keyset.remove(&VK::P);
+        }
+
+        if self.keyset.contains(&VK::Up) {
+            events.push(InputEvent::ScaleParam(self.param_step));
+            self.keyset.remove(&VK::Up);
+        }
+
+        if self.keyset.contains(&VK::Down) {
+            events.push(InputEvent::ScaleParam(1.0 / self.param_step));
+            self.keyset.remove(&VK::Down);
+        }
+
         if self.keyset.contains(&VK::R) {
             events.push(InputEvent::Rebuild);
         }
 
+        if self.moved {
+            events.push(InputEvent::Hover(self.mouse_pos.0, self.mouse_pos.1));
+            self.moved = false;
+        }
+
+        if self.pressed {
+            events.push(InputEvent::Press(self.mouse_pos.0, self.mouse_pos.1));
+            self.pressed = false;
+        }
+
+        if self.right_clicked {
+            events.push(InputEvent::TogglePin(self.mouse_pos.0, self.mouse_pos.1));
+            self.right_clicked = false;
+        }
+
         if self.mouseset[0] {
+            events.push(InputEvent::Drag(self.mouse_pos.0, self.mouse_pos.1));
+
             let drag = (self.mouse_pos.0 - self.mouse_pos_last_pressed.0,
                         self.mouse_pos.1 - self.mouse_pos_last_pressed.1);
             let shift_x = drag.0 as f32 * self.mouse_move_sensitivity;
@@ -129,6 +246,11 @@ impl InputHandler {
             self.mouse_pos_last_pressed = self.mouse_pos;
         }
 
+        if self.released {
+            events.push(InputEvent::Release(self.mouse_pos.0, self.mouse_pos.1));
+            self.released = false;
+        }
+
         if self.zoom != 0.0 {
             events.push(InputEvent::Zoom(self.zoom * self.mouse_scroll_sensitivity));
             self.zoom = 0.0;
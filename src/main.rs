@@ -2,24 +2,69 @@
 extern crate glium;
 extern crate rand;
 extern crate clap;
+extern crate rayon;
 
+mod economy;
+mod export;
+mod font;
 mod graphics;
 mod network;
+mod shader;
 mod simulation;
 mod graph;
 mod input;
 mod util;
 
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+
+use export::Framing;
 use simulation::Simulation;
 use util::get_args;
 
 const NUM_AGENTS: usize = 100;
 const DEGREE_P: f32 = 0.015;
+const DEGREE_K: usize = 4;
+const BETA: f32 = 0.1;
+const BLOCKS: usize = 2;
+const P_OUT: f32 = 0.001;
+const STEPS: usize = 1000;
+const EXPORT_SIZE: (u32, u32) = (1024, 1024);
 
 fn main() {
-    let (n, p) = get_args();
+    let args = get_args();
+
+    eprintln!("Seed: {}", args.seed);
+
+    let mut simulation = Simulation::new(args.n, args.topology, args.seed, args.params, args.integrator);
+    simulation.set_layout(args.layout);
+    simulation.set_economy(args.economy);
+    simulation.set_update_scheme(args.update);
+
+    if !args.headless {
+        simulation.run();
+        return;
+    }
+
+    let result = match args.output {
+        Some(path) => {
+            let file = File::create(&path).expect("Error: Could not create output file");
+            let mut out = BufWriter::new(file);
+
+            simulation.run_headless(args.steps, &mut out).and_then(|stop| out.flush().map(|_| stop))
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut lock = stdout.lock();
+            simulation.run_headless(args.steps, &mut lock)
+        }
+    };
 
-    let mut simulation = Simulation::new(n, p);
+    let stop = result.expect("Error: Could not write results");
+    eprintln!("Stopped: {:?}", stop);
 
-    simulation.run();
+    for path in args.export.iter() {
+        simulation.export(path, args.export_size, Framing::Fit).expect("Error: Could not export image");
+    }
 }
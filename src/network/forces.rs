@@ -0,0 +1,128 @@
+use rayon::prelude::*;
+
+use util::Vec2;
+
+use super::params::PhysicsParams;
+use super::quadtree::QuadTree;
+
+// Snapshot of the network topology on dense indices, agent i of the
+// network is entry i of every position and velocity slice. Every
+// relation is stored as (neighbor, stiffness, rest length).
+pub struct ForceField {
+    adjacency: Vec<Vec<(usize, f32, f32)>>,
+    pinned: Vec<bool>,
+    params: PhysicsParams,
+}
+
+impl ForceField {
+    pub fn new(adjacency: Vec<Vec<(usize, f32, f32)>>, pinned: Vec<bool>, params: PhysicsParams) -> ForceField {
+        ForceField { adjacency: adjacency, pinned: pinned, params: params }
+    }
+
+    // Total force on every agent. Only reads the given state, so the
+    // result does not depend on the order agents are visited in and
+    // agents are processed in parallel. Pinned agents feel no force,
+    // so they stay put as long as they start at rest.
+    pub fn forces(&self, pos: &[Vec2], vel: &[Vec2]) -> Vec<Vec2> {
+        let tree = if self.params.theta > 0.0 {
+            Some(QuadTree::new(pos))
+        } else {
+            None
+        };
+
+        (0..pos.len()).into_par_iter()
+            .map(|i| if self.pinned[i] { Vec2::new(0.0, 0.0) } else { self.force(i, pos, vel, tree.as_ref()) })
+            .collect()
+    }
+
+    // Kinetic energy plus the potential the forces derive from,
+    // decreases over time under damping. Repulsion is summed exactly.
+    pub fn energy(&self, pos: &[Vec2], vel: &[Vec2]) -> f32 {
+        let k_e = self.params.coulomb;
+        let low = self.params.dist_bound;
+        let cent = self.params.centering;
+
+        let mut e = 0.0;
+
+        for i in 0..pos.len() {
+            e += 0.5 * vel[i].length().powi(2);
+            e += 0.5 * cent * pos[i].length().powi(2);
+
+            // Every pair once
+            for &(j, k, rest) in self.adjacency[i].iter().filter(|s| s.0 > i) {
+                e += 0.5 * k * ((pos[j] - pos[i]).length() - rest).powi(2);
+            }
+
+            for j in i+1..pos.len() {
+                let dist = (pos[j] - pos[i]).length();
+
+                // Linear below the bound, matching the constant force there
+                e += if dist < low {
+                    k_e / low + k_e / (low * low) * (low - dist)
+                } else {
+                    k_e / dist
+                };
+            }
+        }
+
+        e
+    }
+
+    fn force(&self, i: usize, pos: &[Vec2], vel: &[Vec2], tree: Option<&QuadTree>) -> Vec2 {
+        let d = self.params.damping;
+        let k_e = self.params.coulomb;
+        let low = self.params.dist_bound;
+        let cent = self.params.centering;
+
+        let posi = pos[i];
+
+        // Spring force
+        let mut f_spring = Vec2::new(0.0, 0.0);
+        for &(j, k, rest) in self.adjacency[i].iter() {
+            let dir = pos[j] - posi;
+            let dist = dir.length();
+
+            f_spring += dir.normalized().scale(k * (dist - rest));
+        }
+
+        // Coulomb force
+        let f_coulomb = match tree {
+            Some(tree) => tree.force(posi, self.params.theta, k_e, low),
+            None => exact_coulomb(pos, i, k_e, low),
+        };
+
+        let mut f = f_spring + f_coulomb;
+
+        // Damping
+        f -= vel[i].scale(d);
+
+        // Centering force
+        // -> to keep the vertices from floating away
+        f += posi.scale(-cent);
+
+        f
+    }
+}
+
+fn exact_coulomb(pos: &[Vec2], i: usize, k_e: f32, low: f32) -> Vec2 {
+    let mut f_coulomb = Vec2::new(0.0, 0.0);
+
+    for j in 0..pos.len() {
+        if i == j {
+            continue;
+        }
+
+        let dir = pos[j] - pos[i];
+        let dist = dir.length();
+
+        let dist_bound = if dist < low {
+            low
+        } else {
+            dist
+        };
+
+        f_coulomb += dir.normalized().scale(-k_e / (dist_bound * dist_bound));
+    }
+
+    f_coulomb
+}
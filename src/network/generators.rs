@@ -0,0 +1,476 @@
+use std::collections::{BTreeMap, HashSet};
+
+use rand::Rng;
+
+use network::{Network, AbstractComponent, get_rand};
+
+// Attempts of the pairing procedure before giving up on a regular graph
+const REGULAR_TRIES: usize = 100;
+
+#[derive(Clone, Debug)]
+pub enum Topology {
+    // G(n, p)
+    ErdosRenyi { p: f32 },
+
+    // Ring where every agent is related to its k nearest agents,
+    // each relation rewired with probability beta
+    WattsStrogatz { k: usize, beta: f32 },
+
+    // Preferential attachment of every new agent to m existing agents
+    BarabasiAlbert { m: usize },
+
+    // Equally sized blocks, related with p_in within and p_out across blocks
+    StochasticBlock { blocks: usize, p_in: f32, p_out: f32 },
+
+    // Every agent has exactly d relations
+    RandomRegular { d: usize },
+
+    // Square grid, wrapped around into a torus if periodic
+    Lattice { periodic: bool },
+
+    Complete,
+    Star,
+    Ring,
+}
+
+impl<T: AbstractComponent> Network<T> {
+    // Networks generated with the same seed and parameters are identical
+    pub fn generate(n: usize, topology: &Topology, seed: u64) -> Network<T> {
+        match *topology {
+            Topology::ErdosRenyi { p } => Network::random(n, p, seed),
+            Topology::WattsStrogatz { k, beta } => Network::watts_strogatz(n, k, beta, seed),
+            Topology::BarabasiAlbert { m } => Network::barabasi_albert(n, m, seed),
+            Topology::StochasticBlock { blocks, p_in, p_out } => {
+                let sizes = (0..blocks).map(|b| n / blocks + if b < n % blocks { 1 } else { 0 }).collect::<Vec<_>>();
+                Network::stochastic_block(&sizes, p_in, p_out, seed)
+            }
+            Topology::RandomRegular { d } => Network::random_regular(n, d, seed),
+            Topology::Lattice { periodic } => {
+                // Largest square with at most n agents
+                let side = (n as f32).sqrt() as usize;
+                Network::lattice(side, side, periodic, seed)
+            }
+            Topology::Complete => Network::complete(n, seed),
+            Topology::Star => Network::star(n, seed),
+            Topology::Ring => Network::ring(n, seed),
+        }
+    }
+
+    // Network of n unrelated agents
+    pub fn with_agents(n: usize, seed: u64) -> Network<T> {
+        let mut network = Network::new(seed);
+
+        for _ in 0..n {
+            let idx = network.add_agent();
+            network.track(idx);
+        }
+
+        network
+    }
+
+    fn relate(&mut self, i: usize, j: usize) {
+        let a = self.nodes[i];
+        let b = self.nodes[j];
+
+        self.add_relation(a, b);
+    }
+
+    // Runs in O(n + m) by skipping over absent relations
+    pub fn random(n: usize, p: f32, seed: u64) -> Network<T> {
+        let mut network = Network::with_agents(n, seed);
+
+        let mut pairs = Vec::new();
+        sample_triangle(&mut network.rng, n, p, |i, j| pairs.push((i, j)));
+
+        for (i, j) in pairs {
+            network.relate(i, j);
+        }
+
+        network
+    }
+
+    pub fn watts_strogatz(n: usize, k: usize, beta: f32, seed: u64) -> Network<T> {
+        let mut network = Network::ring_lattice(n, k / 2, seed);
+
+        for j in 1..k / 2 + 1 {
+            for i in 0..n {
+                if get_rand(&mut network.rng, 0.0, 1.0) >= beta {
+                    continue;
+                }
+
+                let a = network.nodes[i];
+                let b = network.nodes[(i + j) % n];
+
+                // Every other agent is already related to a
+                let degree = network.graph.edges(a).unwrap().len();
+                if degree >= n - 1 || !network.graph.contains_edge(a, b) {
+                    continue;
+                }
+
+                let mut w = network.rng.gen_range(0, n);
+                while w == i || network.graph.contains_edge(a, network.nodes[w]) {
+                    w = network.rng.gen_range(0, n);
+                }
+
+                network.remove_relation(a, b);
+                network.relate(i, w);
+            }
+        }
+
+        network
+    }
+
+    // Starts from a complete network of m + 1 agents
+    pub fn barabasi_albert(n: usize, m: usize, seed: u64) -> Network<T> {
+        let m = m.max(1);
+        let mut network = Network::with_agents(n, seed);
+
+        // Every agent appears once per relation, so sampling
+        // uniformly from this list is proportional to degree
+        let mut targets = Vec::new();
+
+        let start = (m + 1).min(n);
+        for i in 0..start {
+            for j in i+1..start {
+                network.relate(i, j);
+                targets.push(i);
+                targets.push(j);
+            }
+        }
+
+        for i in start..n {
+            let mut chosen = HashSet::new();
+            let mut order = Vec::new();
+
+            while chosen.len() < m.min(i) {
+                let t = targets[network.rng.gen_range(0, targets.len())];
+                if chosen.insert(t) {
+                    order.push(t);
+                }
+            }
+
+            for t in order {
+                network.relate(i, t);
+                targets.push(i);
+                targets.push(t);
+            }
+        }
+
+        network
+    }
+
+    pub fn stochastic_block(sizes: &[usize], p_in: f32, p_out: f32, seed: u64) -> Network<T> {
+        let n = sizes.iter().sum();
+        let mut network = Network::with_agents(n, seed);
+
+        let offsets = sizes.iter().scan(0, |acc, &s| { let o = *acc; *acc += s; Some(o) }).collect::<Vec<_>>();
+
+        let mut pairs = Vec::new();
+        for a in 0..sizes.len() {
+            let oa = offsets[a];
+            sample_triangle(&mut network.rng, sizes[a], p_in, |i, j| pairs.push((oa + i, oa + j)));
+
+            for b in a+1..sizes.len() {
+                let ob = offsets[b];
+                sample_rectangle(&mut network.rng, sizes[a], sizes[b], p_out, |i, j| pairs.push((oa + i, ob + j)));
+            }
+        }
+
+        for (i, j) in pairs {
+            network.relate(i, j);
+        }
+
+        network
+    }
+
+    // Pairing model with the restarts of Steger and Wormald,
+    // n * d has to be even
+    pub fn random_regular(n: usize, d: usize, seed: u64) -> Network<T> {
+        assert!(n * d % 2 == 0, "Error: n * d must be even for a regular network");
+        assert!(d < n, "Error: Degree must be smaller than the number of agents");
+
+        let mut network = Network::with_agents(n, seed);
+
+        for _ in 0..REGULAR_TRIES {
+            if let Some(edges) = try_regular(&mut network.rng, n, d) {
+                for (i, j) in edges {
+                    network.relate(i, j);
+                }
+
+                return network;
+            }
+        }
+
+        panic!("Error: Failed to generate a regular network");
+    }
+
+    pub fn lattice(width: usize, height: usize, periodic: bool, seed: u64) -> Network<T> {
+        let mut network = Network::with_agents(width * height, seed);
+
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+
+                if x + 1 < width {
+                    network.relate(i, i + 1);
+                } else if periodic && width > 2 {
+                    network.relate(i, y * width);
+                }
+
+                if y + 1 < height {
+                    network.relate(i, i + width);
+                } else if periodic && height > 2 {
+                    network.relate(i, x);
+                }
+            }
+        }
+
+        network
+    }
+
+    pub fn complete(n: usize, seed: u64) -> Network<T> {
+        Network::random(n, 1.0, seed)
+    }
+
+    // The first agent is the center
+    pub fn star(n: usize, seed: u64) -> Network<T> {
+        let mut network = Network::with_agents(n, seed);
+
+        for i in 1..n {
+            network.relate(0, i);
+        }
+
+        network
+    }
+
+    pub fn ring(n: usize, seed: u64) -> Network<T> {
+        Network::ring_lattice(n, 1, seed)
+    }
+
+    // Ring where every agent is related to the k closest agents on each side
+    fn ring_lattice(n: usize, k: usize, seed: u64) -> Network<T> {
+        let mut network = Network::with_agents(n, seed);
+
+        for j in 1..k + 1 {
+            for i in 0..n {
+                if j < n {
+                    network.relate(i, (i + j) % n);
+                }
+            }
+        }
+
+        network
+    }
+}
+
+// Number of failed trials before the next success
+fn geometric_skip<R: Rng>(rng: &mut R, log_q: f64) -> usize {
+    let r = rng.gen::<f64>();
+    ((1.0 - r).ln() / log_q).floor() as usize
+}
+
+// Calls f for every pair i > j of 0..n with probability p,
+// after Batagelj and Brandes
+fn sample_triangle<R: Rng, F: FnMut(usize, usize)>(rng: &mut R, n: usize, p: f32, mut f: F) {
+    if p <= 0.0 {
+        return;
+    }
+
+    if p >= 1.0 {
+        for i in 0..n {
+            for j in 0..i {
+                f(i, j);
+            }
+        }
+        return;
+    }
+
+    let log_q = (-(p as f64)).ln_1p();
+
+    let mut v = 1;
+    let mut w: usize = 0;
+    while v < n {
+        w = w.saturating_add(geometric_skip(rng, log_q));
+
+        while w >= v && v < n {
+            w -= v;
+            v += 1;
+        }
+
+        if v < n {
+            f(v, w);
+            w += 1;
+        }
+    }
+}
+
+// Calls f for every pair of 0..rows and 0..cols with probability p
+fn sample_rectangle<R: Rng, F: FnMut(usize, usize)>(rng: &mut R, rows: usize, cols: usize, p: f32, mut f: F) {
+    if p <= 0.0 {
+        return;
+    }
+
+    let total = rows * cols;
+
+    if p >= 1.0 {
+        for k in 0..total {
+            f(k / cols, k % cols);
+        }
+        return;
+    }
+
+    let log_q = (-(p as f64)).ln_1p();
+
+    let mut k = geometric_skip(rng, log_q);
+    while k < total {
+        f(k / cols, k % cols);
+        k = k.saturating_add(geometric_skip(rng, log_q)).saturating_add(1);
+    }
+}
+
+fn try_regular<R: Rng>(rng: &mut R, n: usize, d: usize) -> Option<Vec<(usize, usize)>> {
+    let mut edges = HashSet::new();
+    let mut order = Vec::new();
+
+    let mut stubs = (0..n * d).map(|s| s % n).collect::<Vec<_>>();
+
+    while !stubs.is_empty() {
+        // Number of stubs per agent that could not be paired this round
+        let mut potential = BTreeMap::new();
+        rng.shuffle(&mut stubs);
+
+        for pair in stubs.chunks(2) {
+            let (a, b) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+
+            if a != b && !edges.contains(&(a, b)) {
+                edges.insert((a, b));
+                order.push((a, b));
+            } else {
+                *potential.entry(a).or_insert(0) += 1;
+                *potential.entry(b).or_insert(0) += 1;
+            }
+        }
+
+        // Restart unless some remaining stubs can still be paired
+        let open = potential.keys().cloned().collect::<Vec<_>>();
+        let suitable = potential.is_empty() || open.iter().enumerate().any(|(x, &a)| {
+            open[x+1..].iter().any(|&b| !edges.contains(&(a, b)))
+        });
+
+        if !suitable {
+            return None;
+        }
+
+        stubs = potential.iter().flat_map(|(&a, &c)| ::std::iter::repeat(a).take(c)).collect();
+    }
+
+    Some(order)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use network::{Network, AbstractComponent};
+    use util::SimRng;
+    use network::generators::{sample_triangle, sample_rectangle};
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Dummy;
+
+    impl AbstractComponent for Dummy {
+        fn new(_: &mut SimRng) -> Self {
+            Dummy
+        }
+    }
+
+    fn degrees(network: &Network<Dummy>) -> Vec<usize> {
+        network.nodes().iter().map(|&i| network.graph().edges(i).unwrap().len()).collect()
+    }
+
+    #[test]
+    fn triangle_density() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        let n = 1000;
+        let mut count = 0;
+        sample_triangle(&mut rng, n, 0.01, |i, j| {
+            assert!(j < i && i < n);
+            count += 1;
+        });
+
+        // Expected n (n - 1) / 2 * p = 4995 with standard deviation about 70
+        assert!(count > 4600 && count < 5400);
+    }
+
+    #[test]
+    fn triangle_complete() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        let mut pairs = Vec::new();
+        sample_triangle(&mut rng, 4, 1.0, |i, j| pairs.push((i, j)));
+
+        assert_eq!(pairs.len(), 6);
+
+        let mut count = 0;
+        sample_triangle(&mut rng, 4, 0.0, |_, _| count += 1);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn rectangle_density() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        let mut count = 0;
+        sample_rectangle(&mut rng, 200, 500, 0.05, |i, j| {
+            assert!(i < 200 && j < 500);
+            count += 1;
+        });
+
+        // Expected 5000 with standard deviation about 70
+        assert!(count > 4600 && count < 5400);
+    }
+
+    #[test]
+    fn tiny_density() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        // 1 - p rounds to 1 here, the skips must not collapse to zero
+        let mut count = 0;
+        sample_triangle(&mut rng, 1000, 1e-20, |_, _| count += 1);
+        sample_rectangle(&mut rng, 1000, 1000, 1e-20, |_, _| count += 1);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn deterministic_topologies() {
+        assert_eq!(Network::<Dummy>::complete(6, 0).graph().num_edges(), 15);
+        assert_eq!(Network::<Dummy>::ring(6, 0).graph().num_edges(), 6);
+
+        let star = Network::<Dummy>::star(6, 0);
+        assert_eq!(degrees(&star), vec![5, 1, 1, 1, 1, 1]);
+
+        let grid = Network::<Dummy>::lattice(4, 3, false, 0);
+        assert_eq!(grid.graph().num_edges(), 3 * 3 + 4 * 2);
+
+        let torus = Network::<Dummy>::lattice(4, 3, true, 0);
+        assert!(degrees(&torus).iter().all(|&d| d == 4));
+    }
+
+    #[test]
+    fn random_topologies() {
+        let ws = Network::<Dummy>::watts_strogatz(100, 4, 0.3, 0);
+        assert_eq!(ws.graph().num_edges(), 200);
+
+        let ba = Network::<Dummy>::barabasi_albert(100, 2, 0);
+        assert_eq!(ba.graph().num_edges(), 3 + 97 * 2);
+        assert!(degrees(&ba).iter().all(|&d| d >= 2));
+
+        let regular = Network::<Dummy>::random_regular(50, 3, 0);
+        assert!(degrees(&regular).iter().all(|&d| d == 3));
+
+        let sbm = Network::<Dummy>::stochastic_block(&[10, 10], 1.0, 0.0, 0);
+        assert_eq!(sbm.graph().num_edges(), 2 * 45);
+    }
+}
@@ -0,0 +1,205 @@
+use util::Vec2;
+
+// Accepted error per adaptive step, in units of length
+pub const ADAPTIVE_TOL: f32 = 1e-3;
+
+// Bounds on the adaptive step relative to the requested time step
+const MIN_FRACTION: f32 = 1.0 / 64.0;
+const MAX_GROWTH: f32 = 2.0;
+
+// Time stepping of the layout, every agent has unit mass
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Integrator {
+    SemiImplicitEuler,
+    VelocityVerlet,
+    RungeKutta4,
+    // Heun with an embedded Euler error estimate. Substeps of length h
+    // cover the requested time step, h is carried over between steps.
+    Adaptive { tol: f32, h: f32 },
+}
+
+impl Integrator {
+    pub fn adaptive(tol: f32) -> Integrator {
+        Integrator::Adaptive { tol: tol, h: 0.0 }
+    }
+
+    // Next integrator for cycling through all of them at runtime
+    pub fn next(&self) -> Integrator {
+        match *self {
+            Integrator::SemiImplicitEuler => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::RungeKutta4,
+            Integrator::RungeKutta4 => Integrator::adaptive(ADAPTIVE_TOL),
+            Integrator::Adaptive { .. } => Integrator::SemiImplicitEuler,
+        }
+    }
+
+    // Advances pos and vel by dt, force returns the force on every
+    // agent in the given state
+    pub fn step<F>(&mut self, pos: &mut Vec<Vec2>, vel: &mut Vec<Vec2>, dt: f32, force: F) where F: Fn(&[Vec2], &[Vec2]) -> Vec<Vec2> {
+        match *self {
+            Integrator::SemiImplicitEuler => {
+                let f = force(pos, vel);
+
+                *vel = axpy(vel, dt, &f);
+                *pos = axpy(pos, dt, vel);
+            }
+            Integrator::VelocityVerlet => {
+                // Damping depends on the velocity, so the second force
+                // is evaluated with the half step velocity
+                let a0 = force(pos, vel);
+
+                let half = axpy(vel, 0.5 * dt, &a0);
+                *pos = axpy(pos, dt, &half);
+
+                let a1 = force(pos, &half);
+                *vel = axpy(&half, 0.5 * dt, &a1);
+            }
+            Integrator::RungeKutta4 => {
+                rk4(pos, vel, dt, &force);
+            }
+            Integrator::Adaptive { tol, ref mut h } => {
+                if *h <= 0.0 {
+                    *h = dt;
+                }
+
+                let mut t = 0.0;
+                while t < dt {
+                    let step = h.min(dt - t);
+
+                    let (p, v, err) = heun(pos, vel, step, &force);
+
+                    if err <= tol || step <= dt * MIN_FRACTION {
+                        *pos = p;
+                        *vel = v;
+                        t += step;
+
+                        // A step cut short by the end of dt says
+                        // little about the step size to use next
+                        if step < *h {
+                            continue;
+                        }
+                    }
+
+                    // Second order method, the error scales with step^2
+                    let scale = if err > 0.0 { 0.9 * (tol / err).sqrt() } else { MAX_GROWTH };
+                    *h = (step * scale.min(MAX_GROWTH)).max(dt * MIN_FRACTION).min(dt);
+                }
+            }
+        }
+    }
+}
+
+// x + a * y
+fn axpy(x: &[Vec2], a: f32, y: &[Vec2]) -> Vec<Vec2> {
+    x.iter().zip(y.iter()).map(|(&x, &y)| x + y.scale(a)).collect()
+}
+
+fn rk4<F>(pos: &mut [Vec2], vel: &mut [Vec2], dt: f32, force: &F) where F: Fn(&[Vec2], &[Vec2]) -> Vec<Vec2> {
+    let k1x = vel.to_vec();
+    let k1v = force(pos, vel);
+
+    let p2 = axpy(pos, 0.5 * dt, &k1x);
+    let k2x = axpy(vel, 0.5 * dt, &k1v);
+    let k2v = force(&p2, &k2x);
+
+    let p3 = axpy(pos, 0.5 * dt, &k2x);
+    let k3x = axpy(vel, 0.5 * dt, &k2v);
+    let k3v = force(&p3, &k3x);
+
+    let p4 = axpy(pos, dt, &k3x);
+    let k4x = axpy(vel, dt, &k3v);
+    let k4v = force(&p4, &k4x);
+
+    for i in 0..pos.len() {
+        pos[i] += (k1x[i] + k2x[i].scale(2.0) + k3x[i].scale(2.0) + k4x[i]).scale(dt / 6.0);
+        vel[i] += (k1v[i] + k2v[i].scale(2.0) + k3v[i].scale(2.0) + k4v[i]).scale(dt / 6.0);
+    }
+}
+
+// One Heun step and the largest position difference to explicit Euler
+fn heun<F>(pos: &[Vec2], vel: &[Vec2], dt: f32, force: &F) -> (Vec<Vec2>, Vec<Vec2>, f32) where F: Fn(&[Vec2], &[Vec2]) -> Vec<Vec2> {
+    let a0 = force(pos, vel);
+
+    let p_euler = axpy(pos, dt, vel);
+    let v_euler = axpy(vel, dt, &a0);
+
+    let a1 = force(&p_euler, &v_euler);
+
+    let mut p = Vec::with_capacity(pos.len());
+    let mut v = Vec::with_capacity(pos.len());
+    let mut err = 0.0f32;
+
+    for i in 0..pos.len() {
+        let pi = pos[i] + (vel[i] + v_euler[i]).scale(0.5 * dt);
+
+        err = err.max((pi - p_euler[i]).length());
+
+        p.push(pi);
+        v.push(vel[i] + (a0[i] + a1[i]).scale(0.5 * dt));
+    }
+
+    (p, v, err)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use network::integrator::Integrator;
+    use util::Vec2;
+
+    // Undamped unit oscillator, energy is conserved exactly
+    fn oscillator(pos: &[Vec2], _: &[Vec2]) -> Vec<Vec2> {
+        pos.iter().map(|p| p.scale(-1.0)).collect()
+    }
+
+    fn energy(pos: &[Vec2], vel: &[Vec2]) -> f32 {
+        0.5 * (pos[0].length().powi(2) + vel[0].length().powi(2))
+    }
+
+    // Largest relative energy drift over one period
+    fn drift(mut integrator: Integrator, dt: f32) -> f32 {
+        let mut pos = vec![Vec2::new(1.0, 0.0)];
+        let mut vel = vec![Vec2::new(0.0, 1.0)];
+        let e0 = energy(&pos, &vel);
+
+        let mut max = 0.0f32;
+
+        let steps = (2.0 * ::std::f32::consts::PI / dt) as usize;
+        for _ in 0..steps {
+            integrator.step(&mut pos, &mut vel, dt, oscillator);
+            max = max.max((energy(&pos, &vel) - e0).abs() / e0);
+        }
+
+        max
+    }
+
+    #[test]
+    fn oscillator_energy() {
+        let dt = 0.05;
+
+        let euler = drift(Integrator::SemiImplicitEuler, dt);
+        let verlet = drift(Integrator::VelocityVerlet, dt);
+        let rk4 = drift(Integrator::RungeKutta4, dt);
+        let adaptive = drift(Integrator::adaptive(1e-4), dt);
+
+        assert!(euler < 0.01);
+        assert!(verlet < 1e-4);
+        assert!(rk4 < 1e-4);
+        assert!(adaptive < 1e-3);
+        assert!(verlet < euler && rk4 < euler);
+    }
+
+    #[test]
+    fn cycle() {
+        let start = Integrator::SemiImplicitEuler;
+
+        let mut i = start.next();
+        let mut count = 1;
+        while i != start {
+            i = i.next();
+            count += 1;
+        }
+
+        assert_eq!(count, 4);
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::{BTreeMap, HashMap};
+use std::f32::consts::PI;
+
+use graph::{Graph, NodeIndex};
+use graph::algo::hops;
+use util::Vec2;
+
+// Angle between consecutive points of the starting spiral
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+// Power iterations per Laplacian eigenvector
+const SPECTRAL_ITER: usize = 2000;
+
+// Deterministic layouts, all of them ignore edge direction and place
+// nodes so that adjacent ones are about k apart. Force is the live
+// spring model of Network::physics_tick.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Layout {
+    Force,
+    FruchtermanReingold,
+    KamadaKawai,
+    Spectral,
+    // One circle, agents of a group next to each other
+    Circular,
+    // One concentric circle per group
+    Shell,
+}
+
+impl Layout {
+    // Next layout for cycling through all of them at runtime
+    pub fn next(&self) -> Layout {
+        match *self {
+            Layout::Force => Layout::FruchtermanReingold,
+            Layout::FruchtermanReingold => Layout::KamadaKawai,
+            Layout::KamadaKawai => Layout::Spectral,
+            Layout::Spectral => Layout::Circular,
+            Layout::Circular => Layout::Shell,
+            Layout::Shell => Layout::Force,
+        }
+    }
+}
+
+// Nodes in index order and their neighbors on dense indices
+fn dense<T, S>(graph: &Graph<T, S>) -> (Vec<NodeIndex>, Vec<Vec<usize>>) {
+    let nodes = graph.node_indices().collect::<Vec<_>>();
+    let index = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect::<HashMap<_, _>>();
+
+    let mut adj = vec![Vec::new(); nodes.len()];
+    for (a, b, _) in graph.edges_iter() {
+        let (i, j) = (index[&a], index[&b]);
+
+        if i != j {
+            adj[i].push(j);
+            adj[j].push(i);
+        }
+    }
+
+    for a in adj.iter_mut() {
+        a.sort();
+        a.dedup();
+    }
+
+    (nodes, adj)
+}
+
+fn collect(nodes: Vec<NodeIndex>, pos: Vec<Vec2>) -> HashMap<NodeIndex, Vec2> {
+    nodes.into_iter().zip(pos).collect()
+}
+
+// Sunflower spiral with points about k apart, starting point for
+// the iterative layouts instead of random positions
+fn spiral(n: usize, k: f32) -> Vec<Vec2> {
+    (0..n).map(|i| {
+        let r = k * (i as f32 + 0.5).sqrt();
+        let a = i as f32 * GOLDEN_ANGLE;
+
+        Vec2::new(r * a.cos(), r * a.sin())
+    }).collect()
+}
+
+// Fruchterman-Reingold with a temperature that cools linearly to zero
+pub fn fruchterman_reingold<T, S>(graph: &Graph<T, S>, k: f32, iterations: usize) -> HashMap<NodeIndex, Vec2> {
+    let (nodes, adj) = dense(graph);
+    let n = nodes.len();
+
+    let mut pos = spiral(n, k);
+
+    // A tenth of the initial width
+    let t0 = 0.2 * k * (n as f32).sqrt();
+
+    for it in 0..iterations {
+        let t = t0 * (1.0 - it as f32 / iterations as f32);
+        let mut disp = vec![Vec2::new(0.0, 0.0); n];
+
+        for i in 0..n {
+            for j in i+1..n {
+                let delta = pos[i] - pos[j];
+                let dist = delta.length().max(0.01 * k);
+
+                let f = delta.scale(k * k / (dist * dist));
+                disp[i] += f;
+                disp[j] -= f;
+            }
+
+            for &j in adj[i].iter().filter(|&&j| j > i) {
+                let delta = pos[i] - pos[j];
+
+                let f = delta.scale(delta.length() / k);
+                disp[i] -= f;
+                disp[j] += f;
+            }
+        }
+
+        for i in 0..n {
+            let len = disp[i].length();
+            if len > 0.0 {
+                pos[i] += disp[i].scale(len.min(t) / len);
+            }
+        }
+    }
+
+    collect(nodes, pos)
+}
+
+// Graph distances between all nodes, unreachable pairs are
+// placed one hop further apart than the largest distance
+fn distances<T, S>(graph: &Graph<T, S>, nodes: &[NodeIndex]) -> Vec<Vec<f32>> {
+    let index = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect::<HashMap<_, _>>();
+
+    let mut d = vec![vec![::std::f32::INFINITY; nodes.len()]; nodes.len()];
+    let mut max = 0.0f32;
+
+    for (i, &n) in nodes.iter().enumerate() {
+        for (m, h) in hops(graph, n) {
+            d[i][index[&m]] = h as f32;
+            max = max.max(h as f32);
+        }
+    }
+
+    for row in d.iter_mut() {
+        for x in row.iter_mut() {
+            if x.is_infinite() {
+                *x = max + 1.0;
+            }
+        }
+    }
+
+    d
+}
+
+// Kamada-Kawai energy minimised by localized stress majorization,
+// node pairs are pulled towards k times their graph distance
+pub fn kamada_kawai<T, S>(graph: &Graph<T, S>, k: f32, iterations: usize) -> HashMap<NodeIndex, Vec2> {
+    let nodes = graph.node_indices().collect::<Vec<_>>();
+    let n = nodes.len();
+
+    let d = if graph.is_directed() {
+        // Distances must be symmetric
+        let mut undirected = Graph::new_undirected();
+        let copies = nodes.iter().map(|_| undirected.add_node(())).collect::<Vec<_>>();
+        let index = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect::<HashMap<_, _>>();
+
+        for (a, b, _) in graph.edges_iter() {
+            undirected.add_edge(copies[index[&a]], copies[index[&b]], ());
+        }
+
+        distances(&undirected, &copies)
+    } else {
+        distances(graph, &nodes)
+    };
+
+    let mut pos = spiral(n, k);
+
+    for _ in 0..iterations {
+        for i in 0..n {
+            let mut num = Vec2::new(0.0, 0.0);
+            let mut den = 0.0;
+
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+
+                let target = k * d[i][j];
+                let w = 1.0 / (target * target);
+
+                let delta = pos[i] - pos[j];
+                let dist = delta.length();
+
+                let mut x = pos[j];
+                if dist > 0.0 {
+                    x += delta.scale(target / dist);
+                }
+
+                num += x.scale(w);
+                den += w;
+            }
+
+            if den > 0.0 {
+                pos[i] = num.scale(1.0 / den);
+            }
+        }
+    }
+
+    collect(nodes, pos)
+}
+
+// Coordinates from the eigenvectors of the second and third smallest
+// eigenvalues of the graph Laplacian, scaled to a mean edge length of k
+pub fn spectral<T, S>(graph: &Graph<T, S>, k: f32) -> HashMap<NodeIndex, Vec2> {
+    let (nodes, adj) = dense(graph);
+    let n = nodes.len();
+
+    if n < 3 {
+        return collect(nodes, spiral(n, k));
+    }
+
+    // Largest eigenvectors of c * I - L are the smallest ones of L
+    let max_degree = adj.iter().map(|a| a.len()).max().unwrap();
+    let c = 2.0 * max_degree as f64 + 1.0;
+
+    let mut basis: Vec<Vec<f64>> = vec![vec![1.0 / (n as f64).sqrt(); n]];
+
+    for dim in 0..2 {
+        // Fixed, irregular start so the result is deterministic
+        let mut v = (0..n).map(|i| ((i * (dim + 2)) as f64 * 0.618_034).fract() - 0.5).collect::<Vec<_>>();
+
+        for _ in 0..SPECTRAL_ITER {
+            let mut w = (0..n).map(|i| {
+                (c - adj[i].len() as f64) * v[i] + adj[i].iter().map(|&j| v[j]).sum::<f64>()
+            }).collect::<Vec<_>>();
+
+            for b in basis.iter() {
+                let dot = w.iter().zip(b.iter()).map(|(x, y)| x * y).sum::<f64>();
+                for (x, y) in w.iter_mut().zip(b.iter()) {
+                    *x -= dot * y;
+                }
+            }
+
+            let norm = w.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm == 0.0 {
+                break;
+            }
+
+            v = w.into_iter().map(|x| x / norm).collect();
+        }
+
+        basis.push(v);
+    }
+
+    let mut pos = (0..n).map(|i| Vec2::new(basis[1][i] as f32, basis[2][i] as f32)).collect::<Vec<_>>();
+
+    let edges = adj.iter().enumerate()
+        .flat_map(|(i, a)| a.iter().filter(move |&&j| j > i).map(move |&j| (i, j)))
+        .collect::<Vec<_>>();
+
+    let mean = edges.iter().map(|&(i, j)| (pos[i] - pos[j]).length()).sum::<f32>() / edges.len().max(1) as f32;
+    if mean > 0.0 {
+        for p in pos.iter_mut() {
+            *p = p.scale(k / mean);
+        }
+    }
+
+    collect(nodes, pos)
+}
+
+// Nodes sorted by group, then by index
+fn grouped<T, S, F>(graph: &Graph<T, S>, group: F) -> BTreeMap<usize, Vec<NodeIndex>> where F: Fn(&T) -> usize {
+    let mut groups = BTreeMap::new();
+
+    for n in graph.node_indices() {
+        let g = group(graph.node_payload(n).unwrap());
+        groups.entry(g).or_insert_with(Vec::new).push(n);
+    }
+
+    groups
+}
+
+// Points on a circle of the given radius, starting at the top
+fn ring(n: usize, r: f32) -> Vec<Vec2> {
+    (0..n).map(|i| {
+        let a = PI / 2.0 - 2.0 * PI * i as f32 / n as f32;
+        Vec2::new(r * a.cos(), r * a.sin())
+    }).collect()
+}
+
+// All nodes on one circle with neighbors k apart, groups are contiguous
+pub fn circular<T, S, F>(graph: &Graph<T, S>, k: f32, group: F) -> HashMap<NodeIndex, Vec2> where F: Fn(&T) -> usize {
+    let nodes = grouped(graph, group).into_iter().flat_map(|(_, g)| g).collect::<Vec<_>>();
+    let r = (nodes.len() as f32 * k / (2.0 * PI)).max(k);
+
+    let pos = ring(nodes.len(), r);
+    collect(nodes, pos)
+}
+
+// One circle per group, at least k apart and in group order from the center
+pub fn shell<T, S, F>(graph: &Graph<T, S>, k: f32, group: F) -> HashMap<NodeIndex, Vec2> where F: Fn(&T) -> usize {
+    let mut layout = HashMap::new();
+    let mut r: Option<f32> = None;
+
+    for (_, nodes) in grouped(graph, group) {
+        let min = r.map_or(0.0, |r| r + k);
+
+        // A single node in the innermost shell sits at the center
+        let shell = if r.is_none() && nodes.len() == 1 {
+            0.0
+        } else {
+            (nodes.len() as f32 * k / (2.0 * PI)).max(min).max(k)
+        };
+
+        let pos = ring(nodes.len(), shell);
+        layout.extend(collect(nodes, pos));
+
+        r = Some(shell);
+    }
+
+    layout
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use graph::{Graph, NodeIndex};
+    use network::layout::*;
+
+    // Weighted sum of squared differences between layout and graph distances
+    fn stress(pos: &[Vec2], d: &[Vec<f32>], k: f32) -> f32 {
+        let mut s = 0.0;
+
+        for i in 0..pos.len() {
+            for j in i+1..pos.len() {
+                let target = k * d[i][j];
+                s += ((pos[i] - pos[j]).length() - target).powi(2) / (target * target);
+            }
+        }
+
+        s
+    }
+
+    fn path(n: usize) -> (Graph<usize, ()>, Vec<NodeIndex>) {
+        let mut g = Graph::new_undirected();
+
+        let nodes = (0..n).map(|i| g.add_node(i)).collect::<Vec<_>>();
+        for i in 1..n {
+            g.add_edge(nodes[i-1], nodes[i], ());
+        }
+
+        (g, nodes)
+    }
+
+    // Cycle with a few chords, payload alternates between two groups
+    fn ring_graph(n: usize) -> (Graph<usize, ()>, Vec<NodeIndex>) {
+        let mut g = Graph::new_undirected();
+
+        let nodes = (0..n).map(|i| g.add_node(i % 2)).collect::<Vec<_>>();
+        for i in 0..n {
+            g.add_edge(nodes[i], nodes[(i + 1) % n], ());
+        }
+        for i in (0..n).step_by(5) {
+            g.add_edge(nodes[i], nodes[(i + n / 2) % n], ());
+        }
+
+        (g, nodes)
+    }
+
+    fn same(a: &HashMap<NodeIndex, Vec2>, b: &HashMap<NodeIndex, Vec2>) -> bool {
+        a.len() == b.len() && a.iter().all(|(n, p)| {
+            let q = b[n];
+            p.x().to_bits() == q.x().to_bits() && p.y().to_bits() == q.y().to_bits()
+        })
+    }
+
+    fn dist(l: &HashMap<NodeIndex, Vec2>, a: NodeIndex, b: NodeIndex) -> f32 {
+        (l[&a] - l[&b]).length()
+    }
+
+    #[test]
+    fn deterministic() {
+        let (g, _) = ring_graph(30);
+
+        assert!(same(&fruchterman_reingold(&g, 1.0, 100), &fruchterman_reingold(&g, 1.0, 100)));
+        assert!(same(&kamada_kawai(&g, 1.0, 50), &kamada_kawai(&g, 1.0, 50)));
+        assert!(same(&spectral(&g, 1.0), &spectral(&g, 1.0)));
+        assert!(same(&circular(&g, 1.0, |&x| x), &circular(&g, 1.0, |&x| x)));
+        assert!(same(&shell(&g, 1.0, |&x| x), &shell(&g, 1.0, |&x| x)));
+    }
+
+    #[test]
+    fn fruchterman_reingold_edges() {
+        let (g, nodes) = ring_graph(30);
+        let l = fruchterman_reingold(&g, 1.0, 300);
+
+        // Adjacent nodes end up closer than the average pair
+        let edges = g.edges_iter().map(|(a, b, _)| dist(&l, a, b)).sum::<f32>() / g.num_edges() as f32;
+        let pairs = nodes.iter().flat_map(|&a| nodes.iter().map(move |&b| (a, b)))
+            .filter(|&(a, b)| a != b)
+            .map(|(a, b)| dist(&l, a, b))
+            .collect::<Vec<_>>();
+
+        assert!(edges < 0.5 * pairs.iter().sum::<f32>() / pairs.len() as f32);
+    }
+
+    #[test]
+    fn kamada_kawai_path() {
+        let (g, nodes) = path(8);
+        let l = kamada_kawai(&g, 2.0, 200);
+
+        for i in 1..nodes.len() {
+            assert!((dist(&l, nodes[i-1], nodes[i]) - 2.0).abs() < 0.05);
+        }
+        assert!((dist(&l, nodes[0], nodes[7]) - 14.0).abs() < 0.2);
+
+        // Stress only goes down from the starting spiral
+        let d = distances(&g, &nodes);
+        let before = stress(&spiral(8, 2.0), &d, 2.0);
+        let after = stress(&nodes.iter().map(|n| l[n]).collect::<Vec<_>>(), &d, 2.0);
+        assert!(after < before);
+    }
+
+    #[test]
+    fn spectral_path() {
+        let (g, nodes) = path(10);
+        let l = spectral(&g, 1.0);
+
+        // The Fiedler vector of a path is monotone along it
+        let xs = nodes.iter().map(|n| l[n].x()).collect::<Vec<_>>();
+        let increasing = xs.windows(2).all(|w| w[0] < w[1]);
+        let decreasing = xs.windows(2).all(|w| w[0] > w[1]);
+
+        assert!(increasing || decreasing);
+    }
+
+    #[test]
+    fn circular_groups() {
+        let (g, nodes) = ring_graph(12);
+        let l = circular(&g, 1.0, |&x| x);
+
+        let r = l[&nodes[0]].length();
+        for n in nodes.iter() {
+            assert!((l[n].length() - r).abs() < 1e-4);
+        }
+
+        // Even nodes fill the first half of the circle, clockwise from the top
+        let angle = |n: &NodeIndex| (l[n].x().atan2(l[n].y()) + 2.0 * PI) % (2.0 * PI);
+        for (i, n) in nodes.iter().enumerate() {
+            assert_eq!(angle(n) < PI - 1e-3, i % 2 == 0);
+        }
+    }
+
+    #[test]
+    fn shell_groups() {
+        let (g, nodes) = ring_graph(12);
+        let l = shell(&g, 1.0, |&x| x);
+
+        let r0 = l[&nodes[0]].length();
+        let r1 = l[&nodes[1]].length();
+        assert!(r1 >= r0 + 1.0 - 1e-4);
+
+        for (i, n) in nodes.iter().enumerate() {
+            let r = if i % 2 == 0 { r0 } else { r1 };
+            assert!((l[n].length() - r).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,1249 @@
+use rand::Rng;
+use std::fmt::Debug;
+use std::io;
+use std::io::prelude::*;
+use std::sync::mpsc;
+
+use export::Canvas;
+use util::{Vec2, SimRng, seeded_rng};
+
+use graph::Graph;
+use graph::community::{self, Partition};
+pub use graph::NodeIndex;
+
+mod forces;
+mod generators;
+mod integrator;
+mod layout;
+mod params;
+mod quadtree;
+mod update;
+pub use self::generators::Topology;
+pub use self::integrator::{Integrator, ADAPTIVE_TOL};
+pub use self::layout::Layout;
+pub use self::params::{PhysicsParams, NAMES as PARAM_NAMES};
+pub use self::update::UpdateScheme;
+
+use self::forces::ForceField;
+
+// Mixed into the seed of analyses, e.g. community detection, so they
+// draw from a stream of their own
+const ANALYSIS_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// Upper bound on label propagation sweeps
+const LABEL_PROPAGATION_ITER: usize = 100;
+
+// Radius of the rim drawn around pinned agents, relative to their radius
+const PIN_RIM: f32 = 1.3;
+
+// Rims of the selected agent and its neighbors and the color of
+// their relations, all other relations are faded
+const SELECTED_RIM: f32 = 1.7;
+const NEIGHBOR_RIM: f32 = 1.45;
+const HIGHLIGHT: (f32, f32, f32) = (1.0, 0.6, 0.0);
+const FADED: (f32, f32, f32) = (0.85, 0.85, 0.85);
+
+// Iterations of the iterative layouts
+const FRUCHTERMAN_REINGOLD_ITER: usize = 500;
+const KAMADA_KAWAI_ITER: usize = 300;
+
+// Colors assigned to communities, repeated if there are more communities
+const COMMUNITY_COLORS: [(f32, f32, f32); 8] = [
+    (0.12, 0.47, 0.71),
+    (1.0, 0.5, 0.05),
+    (0.17, 0.63, 0.17),
+    (0.84, 0.15, 0.16),
+    (0.58, 0.4, 0.74),
+    (0.55, 0.34, 0.29),
+    (0.89, 0.47, 0.76),
+    (0.5, 0.5, 0.5),
+];
+
+pub struct Network<T: AbstractComponent> {
+    graph: Graph<Agent<T>, Relation>,
+    nodes: Vec<NodeIndex>,
+
+    // Position of every agent in nodes by its slot in the graph, so
+    // agents are removed without searching nodes
+    positions: Vec<usize>,
+
+    // Bumped whenever agents or relations are added or removed
+    revision: usize,
+
+    seed: u64,
+    rng: SimRng,
+
+    params: PhysicsParams,
+    integrator: Integrator,
+    update: UpdateScheme,
+}
+
+impl<T: AbstractComponent> Network<T> {
+    // All randomness of the network and its agents is drawn from
+    // a generator seeded with seed
+    pub fn new(seed: u64) -> Network<T> {
+        Network {
+            graph: Graph::new_undirected(),
+            nodes: Vec::new(),
+            positions: Vec::new(),
+            revision: 0,
+            seed: seed,
+            rng: seeded_rng(seed),
+            params: PhysicsParams::new(),
+            integrator: Integrator::SemiImplicitEuler,
+            update: UpdateScheme::Synchronous,
+        }
+    }
+
+    // Read access for analyses, e.g. graph::algo
+    pub fn graph(&self) -> &Graph<Agent<T>, Relation> {
+        &self.graph
+    }
+
+    pub fn nodes(&self) -> &[NodeIndex] {
+        &self.nodes
+    }
+
+    // Differs between two calls if the structure of the network changed
+    // in the meantime, e.g. to recompute static layouts only when needed
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    pub fn add_agent(&mut self) -> NodeIndex{
+        self.graph.add_node(Agent::new(Vec2::new(get_rand(&mut self.rng, -20.0, 20.0), get_rand(&mut self.rng, -20.0, 20.0)),
+                                    self.params.agent_r,
+                                    (0.0, 0.0, 0.0),
+                                    T::new(&mut self.rng)
+                                    )
+                         )
+    }
+
+    // Places the child at rest length from its parent and relates it to
+    // the parent and up to kin random neighbors of the parent. Returns
+    // None if the parent no longer exists.
+    pub fn birth(&mut self, parent: NodeIndex, child: T, kin: usize) -> Option<NodeIndex> {
+        let pos = self.graph.node_payload(parent)?.physics.pos;
+
+        let mut neighbors = self.graph.neighbors_iter(&parent).unwrap()
+            .map(|(i, _)| i)
+            .filter(|&i| i != parent)
+            .collect::<Vec<_>>();
+
+        let angle = get_rand(&mut self.rng, 0.0, 2.0 * ::std::f32::consts::PI);
+        let offset = Vec2::new(angle.cos(), angle.sin()).scale(self.params.rest_length);
+
+        let idx = self.graph.add_node(Agent::new(pos + offset, self.params.agent_r, (0.0, 0.0, 0.0), child));
+        self.track(idx);
+        self.add_relation(parent, idx);
+
+        self.rng.shuffle(&mut neighbors);
+        for &n in neighbors.iter().take(kin) {
+            self.add_relation(idx, n);
+        }
+
+        Some(idx)
+    }
+
+    // The last agent in nodes takes the place of the removed one.
+    // Returns whether the agent existed.
+    pub fn remove_agent(&mut self, idx: NodeIndex) -> bool {
+        if !self.graph.remove_node(idx) {
+            return false;
+        }
+        self.revision += 1;
+
+        let pos = self.positions[idx.index()];
+        self.nodes.swap_remove(pos);
+
+        if let Some(&moved) = self.nodes.get(pos) {
+            self.positions[moved.index()] = pos;
+        }
+
+        true
+    }
+
+    // Appends a new agent to nodes
+    fn track(&mut self, idx: NodeIndex) {
+        if self.positions.len() <= idx.index() {
+            self.positions.resize(idx.index() + 1, 0);
+        }
+
+        self.positions[idx.index()] = self.nodes.len();
+        self.nodes.push(idx);
+        self.revision += 1;
+    }
+
+    // Relations are undirected, a single edge is visible from both agents.
+    // Returns false if the relation existed or an agent does not.
+    pub fn add_relation(&mut self, src: NodeIndex, dest: NodeIndex) -> bool {
+        if self.graph.contains_edge(src, dest) || self.graph.add_edge(src, dest, Relation::new((0.0, 0.0, 0.0))).is_none() {
+            return false;
+        }
+
+        self.revision += 1;
+        true
+    }
+
+    // Returns whether the relation existed
+    pub fn remove_relation(&mut self, src: NodeIndex, dest: NodeIndex) -> bool {
+        if !self.graph.remove_edge(src, dest) {
+            return false;
+        }
+
+        self.revision += 1;
+        true
+    }
+
+    // Rules have to draw random numbers from the given generator
+    // for runs to be reproducible. The update scheme decides which
+    // agents run and when their events are applied, see UpdateScheme.
+    // Events of a rule are applied in the order they were sent, so of
+    // two writes to the same agent or relation the later one wins.
+    // Returns the number of events that changed the network, updates
+    // to an identical state do not count.
+    pub fn logic_tick<F>(&mut self, f: F) -> usize where F: Fn(&mpsc::Sender<NetworkEvent<T>>, &mut SimRng, (NodeIndex, &T), &[(NodeIndex, &T)]) {
+        let (tx, rx) = mpsc::channel();
+
+        match self.update {
+            UpdateScheme::Synchronous => {
+                for node in self.nodes.clone() {
+                    self.run_rule(&tx, node, &f);
+                }
+
+                self.apply_events(&rx)
+            }
+            UpdateScheme::RandomSequential => {
+                let mut order = self.nodes.clone();
+                self.rng.shuffle(&mut order);
+
+                let mut count = 0;
+                for node in order {
+                    self.run_rule(&tx, node, &f);
+                    count += self.apply_events(&rx);
+                }
+
+                count
+            }
+            UpdateScheme::Continuous => {
+                let mut time = 0.0;
+                let mut count = 0;
+
+                while !self.nodes.is_empty() {
+                    // Waiting time until any of the agents runs
+                    let rate = self.nodes.len() as f64;
+                    time -= (1.0 - self.rng.gen::<f64>()).ln() / rate;
+                    if time >= 1.0 {
+                        break;
+                    }
+
+                    let node = self.nodes[self.rng.gen_range(0, self.nodes.len())];
+                    self.run_rule(&tx, node, &f);
+                    count += self.apply_events(&rx);
+                }
+
+                count
+            }
+        }
+    }
+
+    // Agents removed earlier in the tick are skipped
+    fn run_rule<F>(&mut self, tx: &mpsc::Sender<NetworkEvent<T>>, node: NodeIndex, f: &F) where F: Fn(&mpsc::Sender<NetworkEvent<T>>, &mut SimRng, (NodeIndex, &T), &[(NodeIndex, &T)]) {
+        let agent = match self.graph.node_payload(node) {
+            Some(agent) => agent,
+            None => return,
+        };
+        let neighbors = self.graph.neighbors_iter(&node).unwrap().map(|(idx, ref agent)| (idx, &agent.logic)).collect::<Vec<_>>();
+
+        f(tx, &mut self.rng, (node, &agent.logic), &neighbors[..]);
+    }
+
+    // Number of pending events that changed the network
+    fn apply_events(&mut self, rx: &mpsc::Receiver<NetworkEvent<T>>) -> usize {
+        let mut count = 0;
+        for e in rx.try_iter() {
+            if self.handle_event(e) {
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    // The index allows looking up per-node results, e.g. from graph::centrality
+    pub fn look_tick<F>(&mut self, f: F) where F: Fn(NodeIndex, &T, &mut PhysicsComponent) {
+        for i in 0..self.nodes.len() {
+            let idx = self.nodes[i];
+            let payload = self.graph.node_payload_mut(idx).unwrap();
+
+            f(idx, &payload.logic, &mut payload.physics);
+        }
+    }
+
+    // Moves every agent to its place in the layout and stops it,
+    // the Force layout leaves positions to physics_tick
+    pub fn apply_layout(&mut self, layout: Layout) {
+        let k = self.params.rest_length;
+
+        let positions = match layout {
+            Layout::Force => return,
+            Layout::FruchtermanReingold => layout::fruchterman_reingold(&self.graph, k, FRUCHTERMAN_REINGOLD_ITER),
+            Layout::KamadaKawai => layout::kamada_kawai(&self.graph, k, KAMADA_KAWAI_ITER),
+            Layout::Spectral => layout::spectral(&self.graph, k),
+            Layout::Circular => layout::circular(&self.graph, k, |a| a.logic.group()),
+            Layout::Shell => layout::shell(&self.graph, k, |a| a.logic.group()),
+        };
+
+        for i in 0..self.nodes.len() {
+            let idx = self.nodes[i];
+            let physics = &mut self.graph.node_payload_mut(idx).unwrap().physics;
+
+            physics.pos = positions[&idx];
+            physics.vel = Vec2::new(0.0, 0.0);
+        }
+    }
+
+    // Draws from a generator of its own, so detecting communities does
+    // not change the course of the simulation
+    pub fn communities(&self, method: CommunityMethod) -> Partition {
+        let mut rng = seeded_rng(self.seed ^ ANALYSIS_SALT);
+
+        match method {
+            CommunityMethod::Louvain => community::louvain(&self.graph, &mut rng),
+            CommunityMethod::LabelPropagation => community::label_propagation(&self.graph, &mut rng, LABEL_PROPAGATION_ITER),
+        }
+    }
+
+    // Color every agent by the community it belongs to
+    pub fn tint_communities(&mut self, partition: &Partition) {
+        for i in 0..self.nodes.len() {
+            let idx = self.nodes[i];
+
+            if let Some(&c) = partition.communities.get(&idx) {
+                let payload = self.graph.node_payload_mut(idx).unwrap();
+                payload.physics.color = COMMUNITY_COLORS[c % COMMUNITY_COLORS.len()];
+            }
+        }
+    }
+
+    // Whether the event changed the network. Events on agents and
+    // relations that were removed earlier in the tick are ignored, as
+    // are relations that already exist.
+    fn handle_event(&mut self, event: NetworkEvent<T>) -> bool {
+        match event {
+            NetworkEvent::AddAgent => {
+                let idx = self.add_agent();
+                self.track(idx);
+                true
+            },
+            NetworkEvent::RemoveAgent(x) => {
+                self.remove_agent(x)
+            },
+            NetworkEvent::AddRelation(x, y) => {
+                self.add_relation(x, y)
+            },
+            NetworkEvent::RemoveRelation(x, y) => {
+                self.remove_relation(x, y)
+            },
+            NetworkEvent::Birth(parent, child, kin) => {
+                self.birth(parent, child, kin).is_some()
+            },
+            NetworkEvent::UpdateAgent(x, state) => {
+                self.modify_agent(x, |logic| *logic = state)
+            },
+            NetworkEvent::ModifyAgent(x, f) => {
+                self.modify_agent(x, |logic| f(logic))
+            },
+            NetworkEvent::UpdateRelation(x, y, relation) => {
+                self.modify_relation(x, y, |r| *r = relation)
+            },
+            NetworkEvent::ModifyRelation(x, y, f) => {
+                self.modify_relation(x, y, |r| f(r))
+            },
+        }
+    }
+
+    // Whether the agent exists and its state changed
+    fn modify_agent<F: FnOnce(&mut T)>(&mut self, idx: NodeIndex, f: F) -> bool {
+        match self.graph.node_payload_mut(idx) {
+            Some(agent) => {
+                let before = agent.logic;
+                f(&mut agent.logic);
+                agent.logic != before
+            }
+            None => false,
+        }
+    }
+
+    fn modify_relation<F: FnOnce(&mut Relation)>(&mut self, src: NodeIndex, dest: NodeIndex, f: F) -> bool {
+        match self.graph.edge_payload_mut(src, dest) {
+            Some(relation) => {
+                let before = *relation;
+                f(relation);
+                *relation != before
+            }
+            None => false,
+        }
+    }
+
+    pub fn params(&self) -> &PhysicsParams {
+        &self.params
+    }
+
+    // Takes effect with the next physics tick, the radius is
+    // applied to all agents right away
+    pub fn set_params(&mut self, params: PhysicsParams) {
+        self.params = params;
+
+        for i in 0..self.nodes.len() {
+            self.graph.node_payload_mut(self.nodes[i]).unwrap().physics.r = params.agent_r;
+        }
+    }
+
+    // Agent whose circle contains pos, the closest one if they overlap
+    pub fn pick(&self, pos: Vec2) -> Option<NodeIndex> {
+        let mut best = None;
+        let mut best_dist = ::std::f32::INFINITY;
+
+        for &idx in self.nodes.iter() {
+            let physics = &self.graph.node_payload(idx).unwrap().physics;
+            let dist = (physics.pos - pos).length();
+
+            if dist <= physics.r && dist < best_dist {
+                best = Some(idx);
+                best_dist = dist;
+            }
+        }
+
+        best
+    }
+
+    // Places an agent at pos and stops it, e.g. while it is dragged
+    pub fn move_agent(&mut self, idx: NodeIndex, pos: Vec2) {
+        if let Some(agent) = self.graph.node_payload_mut(idx) {
+            agent.physics.pos = pos;
+            agent.physics.vel = Vec2::new(0.0, 0.0);
+        }
+    }
+
+    // Pinned agents are skipped by physics_tick
+    pub fn set_pinned(&mut self, idx: NodeIndex, pinned: bool) {
+        if let Some(agent) = self.graph.node_payload_mut(idx) {
+            agent.physics.pinned = pinned;
+            agent.physics.vel = Vec2::new(0.0, 0.0);
+        }
+    }
+
+    // Overrides the global stiffness and rest length for a single
+    // relation, None falls back to the global value
+    pub fn set_spring(&mut self, src: NodeIndex, dest: NodeIndex, stiffness: Option<f32>, rest_length: Option<f32>) -> bool {
+        match self.graph.edge_payload_mut(src, dest) {
+            Some(relation) => {
+                relation.stiffness = stiffness;
+                relation.rest_length = rest_length;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    pub fn update_scheme(&self) -> UpdateScheme {
+        self.update
+    }
+
+    pub fn set_update_scheme(&mut self, update: UpdateScheme) {
+        self.update = update;
+    }
+
+    pub fn physics_tick_till_rest(&mut self, dt: f32, thresh: f32, max: usize) {
+        let mut avg_vel = 2.0*thresh;
+
+        let mut step = 0;
+        while avg_vel > thresh && step < max {
+            self.physics_tick(dt);
+            avg_vel = self.average_vel();
+            step += 1;
+        }
+
+    }
+
+    fn average_vel(&self) -> f32 {
+        let mut total_vel = 0.0;
+        for i in self.graph.nodes_iter() {
+            total_vel += i.physics.vel.abs().horizontal_sum();
+        }
+        total_vel / self.graph.num_nodes() as f32
+    }
+
+    // Force driven smoothing using spring forces to keep 
+    // adjacent vertices at a constant distance and coulomb 
+    // force to keep non-adjacent vertices from clustering.
+    // Forces are computed from the state at the start of the tick.
+    pub fn physics_tick(&mut self, dt: f32) {
+        let field = self.force_field();
+
+        let mut pos = self.positions();
+        let mut vel = self.velocities();
+
+        self.integrator.step(&mut pos, &mut vel, dt, |p, v| field.forces(p, v));
+
+        for i in 0..self.nodes.len() {
+            let physics = &mut self.graph.node_payload_mut(self.nodes[i]).unwrap().physics;
+
+            if !physics.pinned {
+                physics.pos = pos[i];
+                physics.vel = vel[i];
+            }
+        }
+    }
+
+    // Total energy of the layout, see ForceField::energy
+    pub fn energy(&self) -> f32 {
+        self.force_field().energy(&self.positions(), &self.velocities())
+    }
+
+    fn positions(&self) -> Vec<Vec2> {
+        self.nodes.iter()
+            .map(|&i| self.graph.node_payload(i).unwrap().physics.pos)
+            .collect()
+    }
+
+    fn velocities(&self) -> Vec<Vec2> {
+        self.nodes.iter()
+            .map(|&i| self.graph.node_payload(i).unwrap().physics.vel)
+            .collect()
+    }
+
+    // Relations on the same dense indices as self.nodes
+    fn force_field(&self) -> ForceField {
+        let size = self.nodes.iter().map(|i| i.index() + 1).max().unwrap_or(0);
+
+        let mut dense = vec![0; size];
+        for (i, idx) in self.nodes.iter().enumerate() {
+            dense[idx.index()] = i;
+        }
+
+        let pinned = self.nodes.iter()
+            .map(|&i| self.graph.node_payload(i).unwrap().physics.pinned)
+            .collect();
+
+        let adjacency = self.nodes.iter().map(|&idx| {
+            self.graph.edges(idx).unwrap().into_iter()
+                .filter(|&(j, _)| j != idx)
+                .map(|(j, relation)| {
+                    let (k, rest) = relation.spring(&self.params);
+                    (dense[j.index()], k, rest)
+                })
+                .collect()
+        }).collect();
+
+        ForceField::new(adjacency, pinned, self.params)
+    }
+
+    // One line per agent: id, position, degree and the quoted Debug
+    // output of its logic component
+    pub fn write_agents<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "id,x,y,degree,state")?;
+
+        for &idx in self.nodes.iter() {
+            let agent = self.graph.node_payload(idx).unwrap();
+            let degree = self.graph.edges(idx).unwrap().len();
+
+            writeln!(out, "{},{},{},{},\"{:?}\"", idx.index(), agent.physics.pos.x(), agent.physics.pos.y(), degree, agent.logic)?;
+        }
+
+        Ok(())
+    }
+
+    // One line per relation with the ids of both agents
+    pub fn write_relations<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "source,target")?;
+
+        for (a, b, _) in self.graph.edges_iter() {
+            writeln!(out, "{},{}", a.index(), b.index())?;
+        }
+
+        Ok(())
+    }
+
+    // Fields of an agent for display, the logic component is shown
+    // through its Debug implementation
+    pub fn describe(&self, idx: NodeIndex) -> Option<String> {
+        let agent = self.graph.node_payload(idx)?;
+        let degree = self.graph.edges(idx).unwrap().len();
+        let pos = agent.physics.pos;
+
+        let mut text = format!("Agent {}\ndegree: {}\nposition: ({:.2}, {:.2})\n", idx.index(), degree, pos.x(), pos.y());
+        if agent.physics.pinned {
+            text.push_str("pinned\n");
+        }
+        text.push_str(&format!("{:#?}", agent.logic));
+
+        Some(text)
+    }
+
+    // Draws into a frame started by the caller or a file, the selected
+    // agent and its neighbors are highlighted
+    pub fn draw<C: Canvas>(&self, canvas: &mut C, selected: Option<NodeIndex>) {
+        let neighbors = selected.and_then(|s| self.graph.neighbors_iter(&s))
+            .map(|n| n.map(|(i, _)| i).collect::<Vec<_>>())
+            .unwrap_or_else(Vec::new);
+
+        for (i_idx, j_idx, edge_attrib) in self.graph.edges_iter() {
+            let posi = self.graph.node_payload(i_idx).unwrap().physics.pos;
+            let posj = self.graph.node_payload(j_idx).unwrap().physics.pos;
+
+            let color = match selected {
+                Some(s) if s == i_idx || s == j_idx => HIGHLIGHT,
+                Some(_) => FADED,
+                None => edge_attrib.color,
+            };
+
+            canvas.draw_line(posi, posj, color);
+        }
+
+        for i_idx in self.nodes.iter() {
+            let node_i = self.graph.node_payload(*i_idx).unwrap();
+
+            if selected == Some(*i_idx) {
+                canvas.draw_circle(node_i.physics.pos, node_i.physics.r * SELECTED_RIM, HIGHLIGHT);
+            } else if neighbors.contains(i_idx) {
+                canvas.draw_circle(node_i.physics.pos, node_i.physics.r * NEIGHBOR_RIM, HIGHLIGHT);
+            }
+
+            // Dark rim around pinned agents
+            if node_i.physics.pinned {
+                canvas.draw_circle(node_i.physics.pos, node_i.physics.r * PIN_RIM, (0.1, 0.1, 0.1));
+            }
+
+            canvas.draw_circle(node_i.physics.pos, node_i.physics.r, node_i.physics.color)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum CommunityMethod {
+    Louvain,
+    LabelPropagation,
+}
+
+pub enum NetworkEvent<T> {
+    AddAgent,
+    // Child of an agent with its initial state, related to the parent
+    // and the given number of the parent's neighbors
+    Birth(NodeIndex, T, usize),
+    RemoveAgent(NodeIndex),
+    AddRelation(NodeIndex, NodeIndex),
+    RemoveRelation(NodeIndex, NodeIndex),
+    // Replaces the logic component of an agent or changes it in place
+    UpdateAgent(NodeIndex, T),
+    ModifyAgent(NodeIndex, Box<dyn Fn(&mut T)>),
+    // Same for the relation between two agents
+    UpdateRelation(NodeIndex, NodeIndex, Relation),
+    ModifyRelation(NodeIndex, NodeIndex, Box<dyn Fn(&mut Relation)>),
+}
+
+fn get_rand<R: Rng>(rng: &mut R, a: f32, b: f32) -> f32 {
+    (b - a) * rng.gen::<f32>() + a
+}
+
+pub trait AbstractComponent : Copy + Debug + PartialEq {
+    fn new(rng: &mut SimRng) -> Self;
+
+    // Agents of a group are placed together by the circular and shell layouts
+    fn group(&self) -> usize {
+        0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Agent<T: AbstractComponent> {
+    physics: PhysicsComponent,
+    logic: T,
+}
+
+impl<T: AbstractComponent> Agent<T> {
+    fn new(pos: Vec2, r: f32, color: (f32, f32, f32), ac: T) -> Agent<T> {
+        let pc = PhysicsComponent::new(pos, Vec2::new(0.0, 0.0), r, color);
+
+        Agent {
+            physics: pc,
+            logic: ac,
+        }
+    }
+
+    pub fn physics(&self) -> &PhysicsComponent {
+        &self.physics
+    }
+
+    pub fn logic(&self) -> &T {
+        &self.logic
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Relation {
+    pub color: (f32, f32, f32),
+
+    // Spring of this relation, None uses the physics parameters
+    pub stiffness: Option<f32>,
+    pub rest_length: Option<f32>,
+}
+
+impl Relation {
+    pub fn new(color: (f32, f32, f32)) -> Relation {
+        Relation { color: color, stiffness: None, rest_length: None }
+    }
+
+    // Stiffness and rest length of the spring along this relation
+    fn spring(&self, params: &PhysicsParams) -> (f32, f32) {
+        (self.stiffness.unwrap_or(params.stiffness), self.rest_length.unwrap_or(params.rest_length))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PhysicsComponent {
+    pos: Vec2,
+    vel: Vec2,
+    pub r: f32,
+    pub color: (f32, f32, f32),
+
+    pinned: bool,
+}
+
+impl PhysicsComponent {
+    fn new(pos: Vec2, vel: Vec2, r: f32, color: (f32, f32, f32)) -> PhysicsComponent {
+        PhysicsComponent {
+            pos: pos,
+            vel: vel,
+            r: r,
+            color: color,
+            pinned: false,
+        }
+    }
+
+    pub fn pinned(&self) -> bool {
+        self.pinned
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use std::cell::Cell;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    use network::{Network, AbstractComponent, CommunityMethod, NetworkEvent, NodeIndex, Relation, Topology, Integrator, UpdateScheme, PhysicsParams};
+    use util::{Vec2, SimRng};
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct Coin(f32);
+
+    impl AbstractComponent for Coin {
+        fn new(rng: &mut SimRng) -> Self {
+            Coin(rng.gen::<f32>())
+        }
+    }
+
+    // Rule that uses randomness for every kind of event
+    fn churn(tx: &mpsc::Sender<NetworkEvent<Coin>>, rng: &mut SimRng, me: (NodeIndex, &Coin), neighbors: &[(NodeIndex, &Coin)]) {
+        for n in neighbors {
+            if rng.gen::<f32>() < (me.1).0 * 0.1 {
+                tx.send(NetworkEvent::RemoveRelation(me.0, n.0)).unwrap();
+            }
+        }
+
+        if rng.gen::<f32>() < 0.02 {
+            tx.send(NetworkEvent::RemoveAgent(me.0)).unwrap();
+        }
+
+        if rng.gen::<f32>() < 0.02 {
+            tx.send(NetworkEvent::AddAgent).unwrap();
+        }
+
+        if rng.gen::<f32>() < 0.02 {
+            tx.send(NetworkEvent::Birth(me.0, Coin((me.1).0 * 0.5), 2)).unwrap();
+        }
+    }
+
+    type Snapshot = (Vec<(NodeIndex, [u32; 4], Coin)>, Vec<(NodeIndex, NodeIndex)>);
+
+    fn snapshot(network: &Network<Coin>) -> Snapshot {
+        let agents = network.nodes.iter().map(|&i| {
+            let agent = network.graph.node_payload(i).unwrap();
+            let p = &agent.physics;
+
+            (i, [p.pos.x().to_bits(), p.pos.y().to_bits(), p.vel.x().to_bits(), p.vel.y().to_bits()], agent.logic)
+        }).collect();
+
+        let relations = network.graph.edges_iter().map(|(a, b, _)| (a, b)).collect();
+
+        (agents, relations)
+    }
+
+    fn run(seed: u64, communities: bool) -> Snapshot {
+        let mut network = Network::<Coin>::generate(60, &Topology::WattsStrogatz { k: 4, beta: 0.2 }, seed);
+        network.physics_tick_till_rest(0.05, 0.001, 200);
+
+        for _ in 0..10 {
+            if communities {
+                network.communities(CommunityMethod::Louvain);
+            }
+            network.logic_tick(&churn);
+            for _ in 0..10 {
+                network.physics_tick(0.05);
+            }
+        }
+
+        snapshot(&network)
+    }
+
+    #[test]
+    fn reproducible() {
+        let a = run(42, false);
+        let b = run(42, false);
+        let c = run(43, false);
+
+        assert!(a == b);
+        assert!(a != c);
+
+        // Analyses leave the simulation alone
+        assert!(a == run(42, true));
+    }
+
+    // Every agent doubles its coin, the last one is also removed
+    // before its update arrives
+    #[test]
+    fn update_agents() {
+        let mut network = Network::<Coin>::generate(5, &Topology::Ring, 1);
+        let last = *network.nodes.last().unwrap();
+        let before = network.nodes.iter().map(|&i| network.graph.node_payload(i).unwrap().logic).collect::<Vec<_>>();
+
+        let changes = network.logic_tick(|tx, _, me, _| {
+            if me.0 == last {
+                tx.send(NetworkEvent::RemoveAgent(me.0)).unwrap();
+            }
+            tx.send(NetworkEvent::UpdateAgent(me.0, Coin((me.1).0 * 2.0))).unwrap();
+        });
+
+        // One removal and four updates
+        assert_eq!(changes, 5);
+
+        // Nothing changes if agents keep their state
+        assert_eq!(network.logic_tick(|tx, _, me, _| tx.send(NetworkEvent::UpdateAgent(me.0, *me.1)).unwrap()), 0);
+        assert_eq!(network.nodes.len(), 4);
+
+        for (i, &idx) in network.nodes.iter().enumerate() {
+            assert_eq!(network.graph.node_payload(idx).unwrap().logic, Coin(before[i].0 * 2.0));
+        }
+    }
+
+    // Rules read the state from before the tick, so every agent of the
+    // ring ends up with the sum of its neighbors' old coins
+    #[test]
+    fn synchronous_updates() {
+        let mut network = Network::<Coin>::generate(6, &Topology::Ring, 3);
+        let coin = |n: &Network<Coin>, i| n.graph.node_payload(i).unwrap().logic.0;
+
+        let expected = network.nodes.iter()
+            .map(|&i| network.graph.neighbors_iter(&i).unwrap().map(|(j, _)| coin(&network, j)).sum::<f32>())
+            .collect::<Vec<_>>();
+
+        network.logic_tick(|tx, _, me, neighbors| {
+            tx.send(NetworkEvent::UpdateAgent(me.0, Coin(neighbors.iter().map(|n| (n.1).0).sum()))).unwrap();
+        });
+
+        for (k, &i) in network.nodes.iter().enumerate() {
+            assert_eq!(coin(&network, i), expected[k]);
+        }
+    }
+
+    // Events apply in the order agents are visited and send them,
+    // closures see the result of earlier writes
+    #[test]
+    fn update_order() {
+        let mut network = Network::<Coin>::generate(3, &Topology::Ring, 3);
+        let nodes = network.nodes.clone();
+        let target = nodes[0];
+
+        let changes = network.logic_tick(|tx, _, me, _| {
+            if me.0 == nodes[0] {
+                tx.send(NetworkEvent::UpdateAgent(target, Coin(1.0))).unwrap();
+                tx.send(NetworkEvent::ModifyAgent(target, Box::new(|c: &mut Coin| c.0 *= 3.0))).unwrap();
+            } else if me.0 == nodes[1] {
+                tx.send(NetworkEvent::ModifyAgent(target, Box::new(|c: &mut Coin| c.0 += 1.0))).unwrap();
+            }
+        });
+        assert_eq!(network.graph.node_payload(target).unwrap().logic, Coin(4.0));
+        assert_eq!(changes, 3);
+
+        // Conflicting writes, the agent visited last wins
+        network.logic_tick(|tx, _, me, _| {
+            let value = if me.0 == nodes[0] { 10.0 } else { 20.0 };
+            tx.send(NetworkEvent::UpdateAgent(target, Coin(value))).unwrap();
+        });
+        assert_eq!(network.graph.node_payload(target).unwrap().logic, Coin(20.0));
+
+        // Writes to an agent removed earlier in the tick are dropped
+        let changes = network.logic_tick(|tx, _, me, _| {
+            if me.0 == nodes[1] {
+                tx.send(NetworkEvent::RemoveAgent(target)).unwrap();
+                tx.send(NetworkEvent::ModifyAgent(target, Box::new(|c: &mut Coin| c.0 = 0.0))).unwrap();
+            }
+        });
+        assert_eq!(changes, 1);
+    }
+
+    // Events that find nothing to do are not counted
+    #[test]
+    fn structural_changes() {
+        let mut network = Network::<Coin>::generate(4, &Topology::Ring, 3);
+        let (a, b, c) = (network.nodes[0], network.nodes[1], network.nodes[2]);
+
+        let changes = network.logic_tick(|tx, _, me, _| {
+            if me.0 == a {
+                tx.send(NetworkEvent::AddRelation(a, b)).unwrap();
+                tx.send(NetworkEvent::RemoveRelation(a, c)).unwrap();
+                tx.send(NetworkEvent::RemoveAgent(c)).unwrap();
+                tx.send(NetworkEvent::RemoveAgent(c)).unwrap();
+                tx.send(NetworkEvent::Birth(c, Coin(1.0), 1)).unwrap();
+                tx.send(NetworkEvent::AddRelation(a, c)).unwrap();
+            }
+        });
+
+        assert_eq!(changes, 1);
+        assert_eq!(network.nodes.len(), 3);
+    }
+
+    #[test]
+    fn relation_updates() {
+        let mut network = Network::<Coin>::generate(4, &Topology::Ring, 3);
+        let (a, b, c) = (network.nodes[0], network.nodes[1], network.nodes[2]);
+
+        let changes = network.logic_tick(|tx, _, me, _| {
+            if me.0 == a {
+                tx.send(NetworkEvent::UpdateRelation(a, b, Relation::new((1.0, 0.0, 0.0)))).unwrap();
+                tx.send(NetworkEvent::ModifyRelation(b, a, Box::new(|r: &mut Relation| r.stiffness = Some(2.0)))).unwrap();
+
+                // Not related
+                tx.send(NetworkEvent::ModifyRelation(a, c, Box::new(|r: &mut Relation| r.rest_length = Some(3.0)))).unwrap();
+            }
+        });
+
+        let relation = *network.graph.edge_payload(a, b).unwrap();
+        assert_eq!(relation.color, (1.0, 0.0, 0.0));
+        assert_eq!(relation.stiffness, Some(2.0));
+        assert!(network.graph.edge_payload(a, c).is_none());
+        assert_eq!(changes, 2);
+
+        // Writing the same value again changes nothing
+        let changes = network.logic_tick(|tx, _, me, _| {
+            if me.0 == b {
+                tx.send(NetworkEvent::UpdateRelation(a, b, relation)).unwrap();
+            }
+        });
+        assert_eq!(changes, 0);
+    }
+
+    // Only structural changes bump the revision
+    #[test]
+    fn revisions() {
+        let mut network = Network::<Coin>::generate(4, &Topology::Ring, 3);
+        let (a, c) = (network.nodes[0], network.nodes[2]);
+        let revision = network.revision();
+
+        network.logic_tick(|tx, _, me, _| tx.send(NetworkEvent::UpdateAgent(me.0, Coin(5.0))).unwrap());
+        assert_eq!(network.revision(), revision);
+
+        network.add_relation(a, c);
+        network.add_relation(c, a);
+        assert_eq!(network.revision(), revision + 1);
+
+        network.remove_agent(c);
+        network.remove_agent(c);
+        network.remove_relation(a, c);
+        assert_eq!(network.revision(), revision + 2);
+    }
+
+    // Every agent runs once per tick, with random sequential updates
+    // some agents see neighbors that already ran
+    #[test]
+    fn update_schemes() {
+        let seen = Cell::new(0);
+        let mark = |tx: &mpsc::Sender<NetworkEvent<Coin>>, _: &mut SimRng, me: (NodeIndex, &Coin), neighbors: &[(NodeIndex, &Coin)]| {
+            seen.set(seen.get() + neighbors.iter().filter(|n| (n.1).0 >= 10.0).count());
+            tx.send(NetworkEvent::UpdateAgent(me.0, Coin((me.1).0 + 10.0))).unwrap();
+        };
+
+        let mut network = Network::<Coin>::generate(8, &Topology::Ring, 5);
+        assert_eq!(network.logic_tick(&mark), 8);
+        assert_eq!(seen.get(), 0);
+
+        let mut network = Network::<Coin>::generate(8, &Topology::Ring, 5);
+        network.set_update_scheme(UpdateScheme::RandomSequential);
+        assert_eq!(network.logic_tick(&mark), 8);
+        assert!(seen.get() > 0);
+        assert!(network.nodes.iter().all(|&i| network.graph.node_payload(i).unwrap().logic.0 >= 10.0));
+    }
+
+    // Agents run at unit rate, so about once per tick on average
+    #[test]
+    fn continuous_updates() {
+        let mut network = Network::<Coin>::generate(50, &Topology::Ring, 6);
+        network.set_update_scheme(UpdateScheme::Continuous);
+
+        let count = |tx: &mpsc::Sender<NetworkEvent<Coin>>, _: &mut SimRng, me: (NodeIndex, &Coin), _: &[(NodeIndex, &Coin)]| {
+            tx.send(NetworkEvent::UpdateAgent(me.0, Coin((me.1).0 + 1.0))).unwrap();
+        };
+
+        let before = network.nodes.iter().map(|&i| network.graph.node_payload(i).unwrap().logic.0).collect::<Vec<_>>();
+        let changes = (0..20).map(|_| network.logic_tick(&count)).sum::<usize>();
+        let runs = network.nodes.iter().enumerate()
+            .map(|(k, &i)| (network.graph.node_payload(i).unwrap().logic.0 - before[k]).round() as usize)
+            .collect::<Vec<_>>();
+
+        assert_eq!(runs.iter().sum::<usize>(), changes);
+        assert!(changes > 900 && changes < 1100);
+        assert!(runs.iter().any(|&r| r != 20));
+    }
+
+    #[test]
+    fn birth() {
+        let mut network = Network::<Coin>::generate(10, &Topology::Ring, 4);
+        let parent = network.nodes[0];
+        let neighbors = network.graph.neighbors_iter(&parent).unwrap().map(|(i, _)| i).collect::<Vec<_>>();
+
+        let child = network.birth(parent, Coin(0.5), 1).unwrap();
+        assert_eq!(network.nodes.len(), 11);
+        assert_eq!(network.graph.node_payload(child).unwrap().logic, Coin(0.5));
+
+        let dist = (network.graph.node_payload(child).unwrap().physics.pos - network.graph.node_payload(parent).unwrap().physics.pos).length();
+        assert!((dist - network.params.rest_length).abs() < 1e-4);
+
+        // The parent and one of its former neighbors
+        let related = network.graph.neighbors_iter(&child).unwrap().map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(related.len(), 2);
+        assert!(related.contains(&parent));
+        assert!(related.iter().any(|i| neighbors.contains(i)));
+
+        // More kin than neighbors relates the child to all of them
+        let child = network.birth(parent, Coin(0.1), 10).unwrap();
+        assert_eq!(network.graph.edges(child).unwrap().len(), 4);
+
+        network.remove_agent(parent);
+        assert!(network.birth(parent, Coin(0.1), 1).is_none());
+    }
+
+    // Removed agents leave nodes, reused graph slots are tracked again
+    #[test]
+    fn remove_agents() {
+        let mut network = Network::<Coin>::generate(10, &Topology::Ring, 4);
+        let removed = [network.nodes[0], network.nodes[9], network.nodes[4], network.nodes[5]];
+
+        for &idx in removed.iter() {
+            network.remove_agent(idx);
+        }
+        network.remove_agent(removed[0]);
+
+        let idx = network.add_agent();
+        network.track(idx);
+
+        assert_eq!(network.nodes.len(), 7);
+        assert!(removed.iter().all(|i| !network.nodes.contains(i)));
+        for (pos, &idx) in network.nodes.iter().enumerate() {
+            assert!(network.graph.node_payload(idx).is_some());
+            assert_eq!(network.positions[idx.index()], pos);
+        }
+    }
+
+    fn set_theta(network: &mut Network<Coin>, theta: f32) {
+        let mut params = *network.params();
+        params.theta = theta;
+        network.set_params(params);
+    }
+
+    #[test]
+    fn relation_spring() {
+        let mut params = PhysicsParams::new();
+        params.coulomb = 0.0;
+        params.centering = 0.0;
+
+        let mut network = Network::<Coin>::new(1);
+        network.set_params(params);
+
+        for _ in 0..3 {
+            network.handle_event(NetworkEvent::AddAgent);
+        }
+
+        let (a, b, c) = (network.nodes[0], network.nodes[1], network.nodes[2]);
+        network.add_relation(a, b);
+        network.add_relation(b, c);
+        assert!(network.set_spring(b, c, Some(2.0), Some(3.0)));
+        assert!(!network.set_spring(a, c, None, None));
+
+        network.physics_tick_till_rest(0.05, 1e-5, 10_000);
+
+        let pos = |i| network.graph.node_payload(i).unwrap().physics.pos;
+        assert!(((pos(b) - pos(a)).length() - 1.0).abs() < 1e-2);
+        assert!(((pos(c) - pos(b)).length() - 3.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn pinned_agents() {
+        let all = [Integrator::SemiImplicitEuler, Integrator::VelocityVerlet, Integrator::RungeKutta4, Integrator::adaptive(1e-3)];
+
+        for &integrator in all.iter() {
+            let mut network = Network::<Coin>::generate(20, &Topology::Ring, 5);
+            network.set_integrator(integrator);
+
+            let a = network.nodes[0];
+            let b = network.nodes[1];
+            let pos = |n: &Network<Coin>, i| n.graph.node_payload(i).unwrap().physics.pos;
+
+            network.move_agent(a, Vec2::new(50.0, 50.0));
+            assert_eq!(network.pick(Vec2::new(50.1, 49.9)), Some(a));
+            assert_eq!(network.pick(Vec2::new(60.0, 60.0)), None);
+
+            network.set_pinned(a, true);
+            let b0 = pos(&network, b);
+
+            for _ in 0..10 {
+                network.physics_tick(0.05);
+            }
+
+            let p = pos(&network, a);
+            assert!(p.x() == 50.0 && p.y() == 50.0);
+
+            // Its neighbor is still pulled towards it
+            assert!((pos(&network, b) - b0).length() > 0.0);
+
+            network.set_pinned(a, false);
+            network.physics_tick(0.05);
+            assert!(pos(&network, a).x() != 50.0);
+        }
+    }
+
+    #[test]
+    fn describe_agent() {
+        let mut network = Network::<Coin>::generate(10, &Topology::Ring, 2);
+        let a = network.nodes[0];
+
+        let text = network.describe(a).unwrap();
+        assert!(text.starts_with(&format!("Agent {}\n", a.index())));
+        assert!(text.contains("degree: 2\n"));
+        assert!(text.contains("Coin("));
+        assert!(!text.contains("pinned"));
+
+        network.set_pinned(a, true);
+        assert!(network.describe(a).unwrap().contains("pinned\n"));
+
+        network.graph.remove_node(a);
+        assert!(network.describe(a).is_none());
+    }
+
+    // The update of an agent must not see agents moved earlier in the tick
+    #[test]
+    fn order_independent_physics() {
+        let mut a = Network::<Coin>::generate(100, &Topology::WattsStrogatz { k: 4, beta: 0.2 }, 7);
+        let mut b = Network::<Coin>::generate(100, &Topology::WattsStrogatz { k: 4, beta: 0.2 }, 7);
+        set_theta(&mut a, 0.0);
+        set_theta(&mut b, 0.0);
+        b.nodes.reverse();
+
+        // Sums run in a different order, so only a single tick is
+        // compared before rounding differences grow
+        a.physics_tick(0.05);
+        b.physics_tick(0.05);
+
+        for &i in a.nodes.iter() {
+            let pa = a.graph.node_payload(i).unwrap().physics.pos;
+            let pb = b.graph.node_payload(i).unwrap().physics.pos;
+
+            assert!((pa - pb).length() < 1e-4);
+        }
+    }
+
+    // Energy of a fixed layout under every integrator, relative to the start
+    fn energies(integrator: Integrator, dt: f32) -> Vec<f32> {
+        let mut network = Network::<Coin>::generate(30, &Topology::WattsStrogatz { k: 6, beta: 0.1 }, 3);
+        set_theta(&mut network, 0.0);
+        network.set_integrator(integrator);
+
+        let e0 = network.energy();
+
+        (0..400).map(|_| {
+            network.physics_tick(dt);
+            network.energy() / e0
+        }).collect()
+    }
+
+    #[test]
+    fn integrator_convergence() {
+        let all = [Integrator::SemiImplicitEuler, Integrator::VelocityVerlet, Integrator::RungeKutta4, Integrator::adaptive(1e-3)];
+
+        let finals = all.iter().map(|&i| *energies(i, 0.05).last().unwrap()).collect::<Vec<_>>();
+        for e in finals.iter() {
+            assert!(*e < 0.02);
+            assert!((e - finals[0]).abs() < 0.05 * finals[0]);
+        }
+    }
+
+    #[test]
+    fn integrator_large_step() {
+        // Euler keeps oscillating, the higher order methods settle
+        assert!(*energies(Integrator::SemiImplicitEuler, 0.2).last().unwrap() > 0.05);
+        assert!(*energies(Integrator::RungeKutta4, 0.2).last().unwrap() < 0.02);
+
+        // The adaptive step never lets the damped energy grow
+        let e = energies(Integrator::adaptive(1e-3), 0.2);
+        assert!(e.windows(2).all(|w| w[1] <= w[0] * (1.0 + 1e-5)));
+        assert!(*e.last().unwrap() < 0.02);
+    }
+
+    // Mean deviation of relation lengths from the rest length
+    fn spring_error(network: &Network<Coin>) -> f32 {
+        let total = network.graph.edges_iter().map(|(a, b, _)| {
+            let pa = network.graph.node_payload(a).unwrap().physics.pos;
+            let pb = network.graph.node_payload(b).unwrap().physics.pos;
+
+            ((pb - pa).length() - network.params().rest_length).abs()
+        }).sum::<f32>();
+
+        total / network.graph.num_edges() as f32
+    }
+
+    // Barnes-Hut against exact repulsion on the same initial network
+    #[test]
+    #[ignore]
+    fn bench_barnes_hut() {
+        let ticks = 200;
+
+        for &n in [500, 2000, 8000].iter() {
+            for &theta in [0.0, 0.5, 1.0].iter() {
+                let mut network = Network::<Coin>::generate(n, &Topology::BarabasiAlbert { m: 2 }, 42);
+                set_theta(&mut network, theta);
+
+                let start = Instant::now();
+                for _ in 0..ticks {
+                    network.physics_tick(0.05);
+                }
+                let elapsed = start.elapsed();
+                let us = elapsed.as_secs() * 1_000_000 + elapsed.subsec_nanos() as u64 / 1000;
+
+                println!("n = {:>5}, theta = {:.1}: {:>8} us per tick, spring error {:.3}, velocity {:.4}",
+                         n, theta, us / ticks, spring_error(&network), network.average_vel());
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// Radius of agents
+const AGENT_R: f32 = 0.25;
+
+// Rest length of springs
+const REST_LENGTH: f32 = 1.0;
+
+// Damping factor
+const DAMPING: f32 = 0.5;
+
+// Stiffness of spring
+const STIFFNESS: f32 = 1.0;
+
+// Coulomb's constant
+const COULOMB: f32 = 2.0;
+
+// Distance lower bound for Coulomb
+const DIST_BOUND: f32 = 0.1;
+
+// Centering coefficient
+const CENTERING: f32 = 0.05;
+
+// Barnes-Hut opening angle, 0 computes Coulomb exactly
+const THETA: f32 = 0.5;
+
+// Names accepted by get and set, in the order they are cycled through
+pub const NAMES: [&'static str; 8] = [
+    "agent_r",
+    "rest_length",
+    "damping",
+    "stiffness",
+    "coulomb",
+    "dist_bound",
+    "centering",
+    "theta",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhysicsParams {
+    pub agent_r: f32,
+    pub rest_length: f32,
+    pub damping: f32,
+    pub stiffness: f32,
+    pub coulomb: f32,
+    pub dist_bound: f32,
+    pub centering: f32,
+    pub theta: f32,
+}
+
+impl PhysicsParams {
+    pub fn new() -> PhysicsParams {
+        PhysicsParams {
+            agent_r: AGENT_R,
+            rest_length: REST_LENGTH,
+            damping: DAMPING,
+            stiffness: STIFFNESS,
+            coulomb: COULOMB,
+            dist_bound: DIST_BOUND,
+            centering: CENTERING,
+            theta: THETA,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut f32> {
+        match name {
+            "agent_r" => Some(&mut self.agent_r),
+            "rest_length" => Some(&mut self.rest_length),
+            "damping" => Some(&mut self.damping),
+            "stiffness" => Some(&mut self.stiffness),
+            "coulomb" => Some(&mut self.coulomb),
+            "dist_bound" => Some(&mut self.dist_bound),
+            "centering" => Some(&mut self.centering),
+            "theta" => Some(&mut self.theta),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<f32> {
+        let mut copy = *self;
+        copy.field_mut(name).map(|x| *x)
+    }
+
+    // Values have to be finite and non-negative, dist_bound is
+    // divided by and has to be positive
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err(format!("{} must be finite", name));
+        }
+
+        if value < 0.0 {
+            return Err(format!("{} must not be negative", name));
+        }
+
+        if name == "dist_bound" && value == 0.0 {
+            return Err(format!("{} must be positive", name));
+        }
+
+        match self.field_mut(name) {
+            Some(x) => {
+                *x = value;
+                Ok(())
+            }
+            None => Err(format!("unknown parameter {}", name)),
+        }
+    }
+
+    // A single name=value pair, e.g. from the command line
+    pub fn set_pair(&mut self, pair: &str) -> Result<(), String> {
+        let mut split = pair.splitn(2, '=');
+
+        let name = split.next().unwrap().trim();
+        let value = match split.next() {
+            Some(v) => v.trim().parse::<f32>().map_err(|_| format!("{} is not a number", v.trim()))?,
+            None => return Err(format!("expected name = value, got {}", pair)),
+        };
+
+        self.set(name, value)
+    }
+
+    // One name = value pair per line, empty lines and lines
+    // starting with # are skipped. Nothing changes if any line fails.
+    pub fn read_config(&mut self, text: &str) -> Result<(), String> {
+        let mut params = *self;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            params.set_pair(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+
+        *self = params;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use network::params::*;
+
+    #[test]
+    fn names() {
+        let mut params = PhysicsParams::new();
+
+        for (i, name) in NAMES.iter().enumerate() {
+            params.set(name, i as f32).unwrap();
+        }
+
+        for (i, name) in NAMES.iter().enumerate() {
+            assert_eq!(params.get(name), Some(i as f32));
+        }
+
+        assert!(params.get("mass").is_none());
+        assert!(params.set("mass", 1.0).is_err());
+        assert!(params.set("damping", -1.0).is_err());
+    }
+
+    #[test]
+    fn config() {
+        let mut params = PhysicsParams::new();
+
+        let text = "# Longer springs\nrest_length = 2.5\n\n  stiffness=0.5\n";
+        params.read_config(text).unwrap();
+
+        assert_eq!(params.rest_length, 2.5);
+        assert_eq!(params.stiffness, 0.5);
+        assert_eq!(params.damping, PhysicsParams::new().damping);
+
+        let before = params;
+        let err = params.read_config("theta = 0.7\ncoulomb 2").unwrap_err();
+        assert!(err.starts_with("line 2"));
+        assert_eq!(params, before);
+
+        assert!(params.read_config("theta = fast").is_err());
+        assert!(params.read_config("coulomb = inf").is_err());
+        assert!(params.read_config("damping = NaN").is_err());
+        assert!(params.read_config("dist_bound = 0").is_err());
+        assert!(params.read_config("dist_bound = 0.01").is_ok());
+        assert_eq!(params.dist_bound, 0.01);
+    }
+}
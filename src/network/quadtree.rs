@@ -0,0 +1,244 @@
+use util::Vec2;
+
+// Below this cells are not split any further, so coincident
+// positions end up in one leaf instead of recursing forever
+const MAX_DEPTH: usize = 24;
+
+// Barnes-Hut quadtree over agent positions, every agent has unit charge
+pub struct QuadTree {
+    quads: Vec<Quad>,
+}
+
+struct Quad {
+    center: Vec2,
+    half: f32,
+
+    mass: f32,
+    // Sum of positions while building, center of mass afterwards
+    com: Vec2,
+
+    // Index of the first of four consecutive children
+    children: Option<usize>,
+    // Single body of a leaf, kept so it can be pushed down on a split
+    body: Option<Vec2>,
+}
+
+impl Quad {
+    fn new(center: Vec2, half: f32) -> Quad {
+        Quad {
+            center: center,
+            half: half,
+            mass: 0.0,
+            com: Vec2::new(0.0, 0.0),
+            children: None,
+            body: None,
+        }
+    }
+}
+
+impl QuadTree {
+    pub fn new(positions: &[Vec2]) -> QuadTree {
+        let mut min = Vec2::new(0.0, 0.0);
+        let mut max = Vec2::new(0.0, 0.0);
+
+        if let Some(first) = positions.first() {
+            min = *first;
+            max = *first;
+        }
+
+        for p in positions.iter() {
+            min = Vec2::new(min.x().min(p.x()), min.y().min(p.y()));
+            max = Vec2::new(max.x().max(p.x()), max.y().max(p.y()));
+        }
+
+        let center = (min + max).scale(0.5);
+        let half = (max - min).x().max((max - min).y()) * 0.5 + 1e-3;
+
+        let mut tree = QuadTree { quads: vec![Quad::new(center, half)] };
+
+        for &p in positions.iter() {
+            tree.insert(p);
+        }
+
+        for quad in tree.quads.iter_mut() {
+            if quad.mass > 0.0 {
+                quad.com = quad.com.scale(1.0 / quad.mass);
+            }
+        }
+
+        tree
+    }
+
+    fn insert(&mut self, p: Vec2) {
+        let mut q = 0;
+        let mut depth = 0;
+
+        loop {
+            let mass = self.quads[q].mass;
+
+            self.quads[q].mass += 1.0;
+            self.quads[q].com += p;
+
+            if let Some(first) = self.quads[q].children {
+                q = first + self.quadrant(q, p);
+                depth += 1;
+                continue;
+            }
+
+            if mass == 0.0 {
+                self.quads[q].body = Some(p);
+                return;
+            }
+
+            if depth >= MAX_DEPTH {
+                return;
+            }
+
+            // Leaf with a single body, split it and move the body down
+            let old = self.quads[q].body.take().unwrap();
+            let first = self.subdivide(q);
+
+            let c = first + self.quadrant(q, old);
+            self.quads[c].mass = 1.0;
+            self.quads[c].com = old;
+            self.quads[c].body = Some(old);
+
+            q = first + self.quadrant(q, p);
+            depth += 1;
+        }
+    }
+
+    fn subdivide(&mut self, q: usize) -> usize {
+        let first = self.quads.len();
+        let half = self.quads[q].half * 0.5;
+        let center = self.quads[q].center;
+
+        for &(dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].iter() {
+            self.quads.push(Quad::new(center + Vec2::new(dx * half, dy * half), half));
+        }
+
+        self.quads[q].children = Some(first);
+        first
+    }
+
+    // Same order as the children pushed in subdivide
+    fn quadrant(&self, q: usize, p: Vec2) -> usize {
+        let center = self.quads[q].center;
+
+        let east = if p.x() >= center.x() { 1 } else { 0 };
+        let north = if p.y() >= center.y() { 2 } else { 0 };
+
+        east + north
+    }
+
+    // Coulomb force on a unit charge at pos. Cells that appear smaller
+    // than theta from pos are treated as a single charge at their center
+    // of mass, theta = 0 gives the exact sum. Distances are bounded below
+    // by low, charges exactly at pos (the agent itself) are skipped.
+    pub fn force(&self, pos: Vec2, theta: f32, k_e: f32, low: f32) -> Vec2 {
+        let mut f = Vec2::new(0.0, 0.0);
+        let mut stack = vec![0];
+
+        while let Some(q) = stack.pop() {
+            let quad = &self.quads[q];
+            if quad.mass == 0.0 {
+                continue;
+            }
+
+            let dir = quad.com - pos;
+            let dist = dir.length();
+
+            match quad.children {
+                Some(first) if 2.0 * quad.half >= theta * dist => {
+                    stack.extend(first..first + 4);
+                }
+                _ => {
+                    if dist == 0.0 {
+                        continue;
+                    }
+
+                    let dist_bound = if dist < low {
+                        low
+                    } else {
+                        dist
+                    };
+
+                    f += dir.normalized().scale(-k_e * quad.mass / (dist_bound * dist_bound));
+                }
+            }
+        }
+
+        f
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    use network::quadtree::QuadTree;
+    use util::Vec2;
+
+    fn exact(positions: &[Vec2], i: usize, k_e: f32, low: f32) -> Vec2 {
+        let mut f = Vec2::new(0.0, 0.0);
+
+        for (j, &p) in positions.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+
+            let dir = p - positions[i];
+            let dist = dir.length().max(low);
+
+            f += dir.normalized().scale(-k_e / (dist * dist));
+        }
+
+        f
+    }
+
+    fn random_positions(n: usize) -> Vec<Vec2> {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        (0..n).map(|_| Vec2::new(rng.gen_range(-20.0, 20.0), rng.gen_range(-20.0, 20.0))).collect()
+    }
+
+    #[test]
+    fn theta_zero_is_exact() {
+        let positions = random_positions(200);
+        let tree = QuadTree::new(&positions);
+
+        for i in 0..positions.len() {
+            let err = (tree.force(positions[i], 0.0, 2.0, 0.1) - exact(&positions, i, 2.0, 0.1)).length();
+            assert!(err < 1e-4);
+        }
+    }
+
+    #[test]
+    fn approximation_error() {
+        let positions = random_positions(500);
+        let tree = QuadTree::new(&positions);
+
+        let mut err = 0.0;
+        let mut total = 0.0;
+
+        for i in 0..positions.len() {
+            let f = exact(&positions, i, 2.0, 0.1);
+
+            err += (tree.force(positions[i], 0.5, 2.0, 0.1) - f).length();
+            total += f.length();
+        }
+
+        assert!(err / total < 0.02);
+    }
+
+    #[test]
+    fn coincident_positions() {
+        let positions = vec![Vec2::new(1.0, 1.0); 10];
+        let tree = QuadTree::new(&positions);
+
+        let f = tree.force(Vec2::new(2.0, 1.0), 0.5, 1.0, 0.1);
+        assert!((f.x() - 10.0).abs() < 1e-4);
+        assert!(f.y().abs() < 1e-4);
+    }
+}
@@ -0,0 +1,25 @@
+// Order in which the rules of the agents run during a logic tick
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UpdateScheme {
+    // Every agent once, all rules see the state from before the tick
+    // and their events are applied afterwards
+    Synchronous,
+    // Every agent once in a random order, the events of a rule are
+    // applied before the next agent runs
+    RandomSequential,
+    // Every agent runs at the times of a Poisson process with unit
+    // rate, a tick covers one unit of time. Events are applied before
+    // the next agent runs, some agents run several times or not at all.
+    Continuous,
+}
+
+impl UpdateScheme {
+    // Next scheme for cycling through all of them at runtime
+    pub fn next(&self) -> UpdateScheme {
+        match *self {
+            UpdateScheme::Synchronous => UpdateScheme::RandomSequential,
+            UpdateScheme::RandomSequential => UpdateScheme::Continuous,
+            UpdateScheme::Continuous => UpdateScheme::Synchronous,
+        }
+    }
+}
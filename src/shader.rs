@@ -0,0 +1,225 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::time::SystemTime;
+
+use glium::{self, ProgramCreationError};
+use glium::backend::glutin::Display;
+
+// Shader compiled into the binary, path points to the file it was
+// read from so debug builds can pick up changes
+#[derive(Copy, Clone)]
+pub struct Source {
+    pub path: &'static str,
+    pub text: &'static str,
+}
+
+macro_rules! source {
+    ($file:expr) => {
+        Source {
+            path: concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader/", $file),
+            text: include_str!(concat!("shader/", $file)),
+        }
+    }
+}
+
+pub const CIRCLE_VERT: Source = source!("circle_vert.glslv");
+pub const LINE_VERT: Source = source!("line_vert.glslv");
+pub const FRAG: Source = source!("frag_shader.glslf");
+pub const TEXT_VERT: Source = source!("text_vert.glslv");
+pub const TEXT_FRAG: Source = source!("text_frag.glslf");
+
+// Program built from a vertex and a fragment shader
+pub struct Shader {
+    pub program: glium::Program,
+
+    vert: Source,
+    frag: Source,
+
+    // Latest modification of the files the program was built from
+    modified: Option<SystemTime>,
+}
+
+impl Shader {
+    // From the embedded sources, these have to compile
+    pub fn new(display: &Display, vert: Source, frag: Source) -> Shader {
+        let program = compile(display, &vert, vert.text, &frag, frag.text)
+            .unwrap_or_else(|e| panic!("Error: could not compile shaders\n{}", e));
+
+        Shader {
+            program: program,
+            vert: vert,
+            frag: frag,
+            modified: modified(&[vert.path, frag.path]),
+        }
+    }
+
+    // Rebuilds the program if a source file changed since the last
+    // build. A program that does not compile is reported and the old
+    // one is kept. Returns whether the program was replaced.
+    pub fn reload(&mut self, display: &Display) -> bool {
+        let modified = modified(&[self.vert.path, self.frag.path]);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+
+        let result = read(self.vert.path).and_then(|vert| {
+            read(self.frag.path).and_then(|frag| compile(display, &self.vert, &vert, &self.frag, &frag))
+        });
+
+        match result {
+            Ok(program) => {
+                eprintln!("Reloaded {} and {}", self.vert.path, self.frag.path);
+                self.program = program;
+                true
+            }
+            Err(e) => {
+                eprintln!("Error: could not reload shaders\n{}", e);
+                false
+            }
+        }
+    }
+}
+
+fn compile(display: &Display, vert: &Source, vert_text: &str, frag: &Source, frag_text: &str) -> Result<glium::Program, String> {
+    let vert_text = numbered(vert_text, 1);
+    let frag_text = numbered(frag_text, 2);
+
+    glium::Program::from_source(display, &vert_text, &frag_text, None).map_err(|e| match e {
+        ProgramCreationError::CompilationError(ref log, ..) |
+        ProgramCreationError::LinkingError(ref log) => locate_errors(log, &[vert.path, frag.path]),
+        e => format!("{:?}", e),
+    })
+}
+
+fn read(path: &str) -> Result<String, String> {
+    let mut text = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    Ok(text)
+}
+
+// Latest modification time of all files, None if one cannot be read
+fn modified(paths: &[&str]) -> Option<SystemTime> {
+    let mut latest = None;
+
+    for path in paths {
+        let time = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        latest = latest.max(Some(time));
+    }
+
+    latest
+}
+
+// Sets the source string number of a shader, drivers report errors as
+// number:line or number(line), so the number identifies the file
+fn numbered(text: &str, index: usize) -> String {
+    if text.starts_with("#version") {
+        let end = text.find('\n').map_or(text.len(), |i| i + 1);
+        format!("{}#line 2 {}\n{}", &text[..end], index, &text[end..])
+    } else {
+        format!("#line 1 {}\n{}", index, text)
+    }
+}
+
+// Replaces the number:line prefixes of a driver log by file:line,
+// paths[0] belongs to source string number 1. Lines in other formats
+// are kept as they are.
+fn locate_errors(log: &str, paths: &[&str]) -> String {
+    let mut lines = Vec::new();
+
+    for line in log.lines() {
+        let located = split_location(line).and_then(|(index, line, message)| {
+            paths.get(index.wrapping_sub(1)).map(|path| format!("{}:{}: {}", path, line, message))
+        });
+
+        lines.push(located.unwrap_or_else(|| line.to_string()));
+    }
+
+    lines.join("\n")
+}
+
+// Source string number, line and message of "1:12(5): error" (Mesa),
+// "1(12) : error" (NVIDIA) and "ERROR: 1:12: error" (AMD, Intel)
+fn split_location(line: &str) -> Option<(usize, usize, String)> {
+    let (severity, rest) = match line.find(": ") {
+        Some(i) if line[..i].chars().all(|c| c.is_ascii_uppercase()) && i > 0 => (&line[..i], &line[i + 2..]),
+        _ => ("", line),
+    };
+
+    let (index, rest) = number(rest)?;
+
+    let (line_no, rest) = if rest.starts_with(':') {
+        let (line_no, rest) = number(&rest[1..])?;
+
+        // Column in parentheses
+        let rest = if rest.starts_with('(') {
+            &rest[rest.find(')')? + 1..]
+        } else {
+            rest
+        };
+
+        (line_no, rest)
+    } else if rest.starts_with('(') {
+        let (line_no, rest) = number(&rest[1..])?;
+        if !rest.starts_with(')') {
+            return None;
+        }
+
+        (line_no, &rest[1..])
+    } else {
+        return None;
+    };
+
+    let message = rest.trim_start_matches(|c| c == ':' || c == ' ');
+
+    if severity.is_empty() {
+        Some((index, line_no, message.to_string()))
+    } else {
+        Some((index, line_no, format!("{}: {}", severity.to_lowercase(), message)))
+    }
+}
+
+// Leading decimal number and the rest of the string
+fn number(text: &str) -> Option<(usize, &str)> {
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let n = text[..end].parse().ok()?;
+
+    Some((n, &text[end..]))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use shader::*;
+    use super::{numbered, locate_errors};
+
+    #[test]
+    fn embedded() {
+        for source in [CIRCLE_VERT, LINE_VERT, FRAG, TEXT_VERT, TEXT_FRAG].iter() {
+            assert!(source.text.starts_with("#version"));
+            assert!(source.path.ends_with(".glslv") || source.path.ends_with(".glslf"));
+        }
+    }
+
+    #[test]
+    fn line_numbers() {
+        assert_eq!(numbered("#version 330\nvoid main() {}\n", 2), "#version 330\n#line 2 2\nvoid main() {}\n");
+        assert_eq!(numbered("void main() {}", 1), "#line 1 1\nvoid main() {}");
+    }
+
+    #[test]
+    fn error_locations() {
+        let paths = ["a.glslv", "b.glslf"];
+
+        assert_eq!(locate_errors("1:12(5): error: syntax error", &paths), "a.glslv:12: error: syntax error");
+        assert_eq!(locate_errors("2(7) : error C0000: syntax error", &paths), "b.glslf:7: error C0000: syntax error");
+        assert_eq!(locate_errors("ERROR: 2:3: 'x' : undeclared identifier", &paths), "b.glslf:3: error: 'x' : undeclared identifier");
+
+        // Unknown source numbers and other lines are left alone
+        assert_eq!(locate_errors("3:1(1): error: x\nlinking failed", &paths), "3:1(1): error: x\nlinking failed");
+    }
+}
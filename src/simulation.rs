@@ -1,12 +1,14 @@
+use export::{self, Drawing, Framing};
 use graphics::Renderer;
-use network::{ Network, AbstractComponent, NetworkEvent, NodeIndex, PhysicsComponent };
+use economy::{self, EconomyParams, LogicComponent, ProductionType};
+use network::{ Network, NodeIndex, PhysicsComponent, CommunityMethod, Topology, Integrator, UpdateScheme, PhysicsParams, PARAM_NAMES, Layout };
 
 use input::InputEvent;
 use input::InputHandler;
 
-use rand;
-use rand::Rng;
-use std::sync::mpsc;
+use std::io;
+use std::io::prelude::*;
+use std::time::Instant;
 
 use util::Ticker;
 
@@ -14,129 +16,471 @@ const TIME_STEP: f32 = 0.05;
 const VEL_THRESH: f32 = 0.001;
 const IT_THRESH: usize = 2_000;
 
+// Physics ticks per second and seconds between logic and look ticks
+// in the windowed loop at normal speed
+const PHYSICS_RATE: usize = 30;
+const LOGIC_INTERVAL: usize = 4;
+const LOOK_INTERVAL: usize = 2;
+
+// Keeps the headless loop at the same pace as the windowed one
+const PHYSICS_PER_LOGIC: usize = PHYSICS_RATE * LOGIC_INTERVAL;
+
+// Factors of the normal speed, [ and ] halve and double it
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 64.0;
+
+// Upper bound on ticks of one kind per frame, the simulation runs slower
+// than requested rather than falling further behind
+const MAX_TICKS_PER_FRAME: usize = 64;
+
+// Inspector panel in window pixels
+const PANEL_POS: (f32, f32) = (10.0, 10.0);
+const PANEL_PADDING: f32 = 8.0;
+const PANEL_TEXT: f32 = 20.0;
+
+// Smoothing of the frame time shown on the HUD, weight of the latest frame
+const FRAME_SMOOTHING: f32 = 0.05;
+
 pub struct Simulation {
-    renderer: Renderer,
     network: Network<LogicComponent>,
-    input: InputHandler,
 
     n: usize,
-    p: f32,
+    topology: Topology,
+    seed: u64,
+
+    economy: EconomyParams,
 
     freeze: bool,
+    speed: f32,
+    show_communities: bool,
+    show_hud: bool,
+
+    // Simulated time of the physics and number of logic ticks
+    time: f32,
+    ticks: usize,
+    layout: Layout,
+
+    // Physics parameter adjusted from the keyboard, index into PARAM_NAMES
+    param: usize,
+
+    // Agent held by the mouse and whether it was pinned before
+    dragging: Option<(NodeIndex, bool)>,
+
+    // Agent shown in the inspector, the one under the cursor is
+    // shown while nothing is selected
+    selected: Option<NodeIndex>,
+    hovered: Option<NodeIndex>,
+
+    // Where a press missed every agent, releasing there clears the selection
+    missed: Option<(f64, f64)>,
+}
+
+// Why a headless run ended
+#[derive(Copy, Clone, Debug)]
+pub enum Stop {
+    StepLimit,
+    // A logic tick did not change the network
+    SteadyState,
+    Extinct,
 }
 
 impl Simulation {
-    pub fn new(n: usize, p: f32) -> Simulation {
-        let mut network = Network::random(n, p);
+    pub fn new(n: usize, topology: Topology, seed: u64, params: PhysicsParams, integrator: Integrator) -> Simulation {
+        let mut network = Network::generate(n, &topology, seed);
+        network.set_params(params);
+        network.set_integrator(integrator);
         network.physics_tick_till_rest(TIME_STEP, VEL_THRESH, IT_THRESH);
 
-        let renderer = Renderer::new();
-        let input = InputHandler::new();
-
         Simulation {
             n: n,
-            p: p,
-            renderer: renderer,
+            topology: topology,
+            seed: seed,
             network: network,
-            input: input,
-            freeze: true,
+            economy: EconomyParams::new(),
+            freeze: false,
+            speed: 1.0,
+            show_communities: false,
+            show_hud: true,
+            time: 0.0,
+            ticks: 0,
+            layout: Layout::Force,
+            param: 0,
+            dragging: None,
+            selected: None,
+            hovered: None,
+            missed: None,
         }
     }
 
+    pub fn set_economy(&mut self, economy: EconomyParams) {
+        self.economy = economy;
+    }
+
+    pub fn set_update_scheme(&mut self, update: UpdateScheme) {
+        self.network.set_update_scheme(update);
+    }
+
+    // Positions come from physics_tick for the Force layout, all other
+    // layouts are static and recomputed whenever agents or relations
+    // are added or removed
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+        self.network.apply_layout(layout);
+    }
+
     pub fn run(&mut self) {
-        let mut physics_ticker = Ticker::new(2);
-        let mut logic_ticker = Ticker::new(240);
-        let mut look_ticker = Ticker::new(120);
-
-        let update_state = |tx: &mpsc::Sender<NetworkEvent>, me: (NodeIndex, &LogicComponent), neighbors: &[(NodeIndex, &LogicComponent)]| {
-            for n in neighbors {
-                if n.1.ptype != me.1.ptype {
-                    tx.send(NetworkEvent::RemoveRelation(me.0, n.0)).unwrap();
-                }
-            }
-        };
+        let mut renderer = Renderer::new();
+        let mut input = InputHandler::new();
 
-        let update_look = |logic: &LogicComponent, physics: &mut PhysicsComponent|  {
-            physics.color = if logic.ptype == ProductionType::Hunter {
-                (0.9, 0.08, 0.04)
-            } else {
-                (0.0, 0.6, 0.1)
-            };
-        };
+        let mut physics_ticker = Ticker::new(1.0 / PHYSICS_RATE as f32);
+        let mut logic_ticker = Ticker::new(LOGIC_INTERVAL as f32);
+        let mut look_ticker = Ticker::new(LOOK_INTERVAL as f32);
+
+        let mut last_frame = Instant::now();
+        let mut frame_time = 0.0;
 
         loop {
-            self.network.draw(&mut self.renderer);
+            renderer.begin_frame();
+            renderer.clear_color(1.0, 1.0, 1.0);
+            self.network.draw(&mut renderer, self.selected);
+            self.draw_inspector(&mut renderer);
+            if self.show_hud {
+                self.draw_hud(&mut renderer, frame_time);
+            }
+            renderer.end_frame();
 
-            if physics_ticker.tick() {
-                self.network.physics_tick(TIME_STEP);
+            let elapsed = last_frame.elapsed();
+            last_frame = Instant::now();
+            let secs = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+            frame_time += (secs - frame_time) * FRAME_SMOOTHING;
+
+            // Simulated seconds at the current speed, nothing while paused
+            let dt = if self.freeze { 0.0 } else { secs * self.speed };
+
+            for _ in 0..physics_ticker.advance(dt, MAX_TICKS_PER_FRAME) {
+                if self.layout == Layout::Force {
+                    self.network.physics_tick(TIME_STEP);
+                    self.time += TIME_STEP;
+                }
             }
 
-            if logic_ticker.tick() {
-                self.network.logic_tick(&update_state);
+            for _ in 0..logic_ticker.advance(dt, MAX_TICKS_PER_FRAME) {
+                self.step_logic();
             }
 
-            if look_ticker.tick() {
-                self.network.look_tick(&update_look);
+            if look_ticker.advance(dt, MAX_TICKS_PER_FRAME) > 0 {
+                self.look_tick();
             }
 
-            {
-                let renderer = &mut self.renderer;
-                let input = &mut self.input;
+            renderer.event_loop.poll_events(|e| input.handle_events(e));
 
-                renderer.event_loop.poll_events(|e| input.handle_events(e));
+            let events = input.get_events();
+            if self.assign_events(&mut renderer, events) {
+                return;
             }
+        }
+    }
 
-            let events = self.input.get_events();
-            if self.assign_events(events) {
-                return;
+    // Logic tick of the windowed loop, static layouts are recomputed
+    // only if agents or relations were added or removed
+    fn step_logic(&mut self) {
+        let revision = self.network.revision();
+        self.logic_tick();
+
+        if self.network.revision() != revision {
+            self.network.apply_layout(self.layout);
+        }
+    }
+
+    // Fields of the selected or hovered agent in a panel in the top left
+    // corner, agents that died in the meantime are dropped
+    fn draw_inspector(&mut self, renderer: &mut Renderer) {
+        if self.selected.map_or(false, |idx| self.network.describe(idx).is_none()) {
+            self.selected = None;
+        }
+
+        let text = match self.selected.or(self.hovered).and_then(|idx| self.network.describe(idx)) {
+            Some(text) => text,
+            None => return,
+        };
+
+        let (w, h) = renderer.text_extent(&text, PANEL_TEXT);
+        renderer.fill_rect(PANEL_POS, (w + 2.0 * PANEL_PADDING, h + 2.0 * PANEL_PADDING), (0.95, 0.95, 0.95), 0.85);
+        renderer.draw_text((PANEL_POS.0 + PANEL_PADDING, PANEL_POS.1 + PANEL_PADDING), &text, (0.0, 0.0, 0.0), PANEL_TEXT);
+    }
+
+    // Simulation state in the top right corner, frame_time in seconds
+    fn draw_hud(&self, renderer: &mut Renderer, frame_time: f32) {
+        let hunters = self.count(ProductionType::Hunter);
+        let name = PARAM_NAMES[self.param];
+
+        let mut text = format!("time: {:.1}\nticks: {}\n", self.time, self.ticks);
+        if self.freeze {
+            text.push_str(&format!("paused ({}x)\n", self.speed));
+        } else {
+            text.push_str(&format!("running at {}x\n", self.speed));
+        }
+        text.push_str(&format!("update: {:?}\n", self.network.update_scheme()));
+        text.push_str(&format!("agents: {}\nrelations: {}\n", self.network.nodes().len(), self.network.graph().num_edges()));
+        text.push_str(&format!("hunters: {}\ngatherers: {}\n", hunters, self.network.nodes().len() - hunters));
+        text.push_str(&format!("{}: {:.3}\n", name, self.network.params().get(name).unwrap()));
+        text.push_str(&format!("frame: {:.1} ms ({:.0} fps)", frame_time * 1e3, 1.0 / frame_time.max(1e-6)));
+
+        let (width, _) = renderer.screen_size();
+        let (w, h) = renderer.text_extent(&text, PANEL_TEXT);
+        let pos = (width - PANEL_POS.0 - w - 2.0 * PANEL_PADDING, PANEL_POS.1);
+
+        renderer.fill_rect(pos, (w + 2.0 * PANEL_PADDING, h + 2.0 * PANEL_PADDING), (0.95, 0.95, 0.95), 0.85);
+        renderer.draw_text((pos.0 + PANEL_PADDING, pos.1 + PANEL_PADDING), &text, (0.0, 0.0, 0.0), PANEL_TEXT);
+    }
+
+    // Draws the network without selection to an .svg or .png file
+    pub fn export(&self, path: &str, size: (u32, u32), framing: Framing) -> io::Result<()> {
+        let mut drawing = Drawing::new();
+        self.network.draw(&mut drawing, None);
+
+        export::write_file(path, &drawing, size, framing)
+    }
+
+    fn count(&self, ptype: ProductionType) -> usize {
+        self.network.nodes().iter()
+            .filter(|&&i| self.network.graph().node_payload(i).unwrap().logic().ptype == ptype)
+            .count()
+    }
+
+    // Runs without a window until the step limit or a stop condition
+    // is reached and writes the final state to out
+    pub fn run_headless<W: Write>(&mut self, steps: usize, out: &mut W) -> io::Result<Stop> {
+        let mut stop = Stop::StepLimit;
+        let mut step = 0;
+
+        while step < steps {
+            if self.layout == Layout::Force {
+                for _ in 0..PHYSICS_PER_LOGIC {
+                    self.network.physics_tick(TIME_STEP);
+                }
+            }
+
+            let changes = self.logic_tick();
+            step += 1;
+
+            if self.network.nodes().is_empty() {
+                stop = Stop::Extinct;
+                break;
+            }
+
+            if changes == 0 {
+                stop = Stop::SteadyState;
+                break;
             }
         }
+
+        self.network.apply_layout(self.layout);
+        self.look_tick();
+        self.write_results(out, step, stop)?;
+
+        Ok(stop)
+    }
+
+    // Agents first make their offers and then trade, produce and
+    // consume. Returns the number of changes to the network.
+    fn logic_tick(&mut self) -> usize {
+        let economy = self.economy;
+        self.ticks += 1;
+
+        self.network.logic_tick(|tx, rng, me, neighbors| economy::offer(&economy, tx, rng, me, neighbors)) +
+        self.network.logic_tick(|tx, rng, me, neighbors| economy::trade(&economy, tx, rng, me, neighbors)) +
+        self.network.logic_tick(|tx, rng, me, neighbors| economy::live(&economy, tx, rng, me, neighbors))
+    }
+
+    fn look_tick(&mut self) {
+        self.network.look_tick(&update_look);
+
+        if self.show_communities {
+            let partition = self.network.communities(CommunityMethod::Louvain);
+            self.network.tint_communities(&partition);
+        }
+    }
+
+    fn write_results<W: Write>(&self, out: &mut W, steps: usize, stop: Stop) -> io::Result<()> {
+        let hunters = self.count(ProductionType::Hunter);
+
+        writeln!(out, "# seed {}", self.seed)?;
+        writeln!(out, "# agents {}", self.n)?;
+        writeln!(out, "# topology {:?}", self.topology)?;
+        writeln!(out, "# layout {:?}", self.layout)?;
+        writeln!(out, "# integrator {:?}", self.network.integrator())?;
+        writeln!(out, "# update {:?}", self.network.update_scheme())?;
+        for name in PARAM_NAMES.iter() {
+            writeln!(out, "# {} {}", name, self.network.params().get(name).unwrap())?;
+        }
+        for (name, value) in self.economy.pairs() {
+            writeln!(out, "# {} {}", name, value)?;
+        }
+        writeln!(out, "# steps {}", steps)?;
+        writeln!(out, "# stop {:?}", stop)?;
+        writeln!(out, "# alive {}", self.network.nodes().len())?;
+        writeln!(out, "# hunters {}", hunters)?;
+        writeln!(out, "# gatherers {}", self.network.nodes().len() - hunters)?;
+        writeln!(out, "# relations {}", self.network.graph().num_edges())?;
+
+        self.network.write_agents(out)?;
+        self.network.write_relations(out)
     }
 
-    fn assign_events(&mut self, events: Vec<InputEvent>) -> bool {
+    fn assign_events(&mut self, renderer: &mut Renderer, events: Vec<InputEvent>) -> bool {
         for e in events.iter() {
             if let &InputEvent::ToggleFreeze = e {
                 self.freeze = !self.freeze;
             }
-            if let &InputEvent::Quit = e {
-                return true;
+            if let &InputEvent::Step = e {
+                self.step_logic();
+                self.look_tick();
             }
-            if let &InputEvent::Rebuild = e {
-                self.network = Network::random(self.n, self.p);
-                self.network.physics_tick_till_rest(TIME_STEP, VEL_THRESH, IT_THRESH);
+            if let &InputEvent::ScaleSpeed(factor) = e {
+                self.speed = (self.speed * factor).max(MIN_SPEED).min(MAX_SPEED);
+                eprintln!("Speed: {}x", self.speed);
             }
-        }
+            if let &InputEvent::ToggleCommunities = e {
+                self.show_communities = !self.show_communities;
+            }
+            if let &InputEvent::ToggleHud = e {
+                self.show_hud = !self.show_hud;
+            }
+            if let &InputEvent::Export = e {
+                let (width, height) = renderer.screen_size();
+                let (center, zoom) = renderer.view();
+                let framing = Framing::View { center: center, zoom: zoom };
 
-        self.renderer.apply_events(&events);
-        false
-    }
-}
+                for extension in ["svg", "png"].iter() {
+                    let path = format!("agents-{}-{}.{}", self.seed, self.ticks, extension);
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-enum ProductionType {
-    Hunter,
-    Gatherer,
-}
+                    match self.export(&path, (width as u32, height as u32), framing) {
+                        Ok(()) => eprintln!("Exported {}", path),
+                        Err(e) => eprintln!("Error: Could not export {}: {}", path, e),
+                    }
+                }
+            }
+            if let &InputEvent::CycleIntegrator = e {
+                let integrator = self.network.integrator().next();
+                eprintln!("Integrator: {:?}", integrator);
 
-#[derive(Copy, Clone, Debug)]
-struct LogicComponent {
-    ptype: ProductionType,
+                self.network.set_integrator(integrator);
+            }
+            if let &InputEvent::CycleUpdateScheme = e {
+                let update = self.network.update_scheme().next();
+                eprintln!("Update scheme: {:?}", update);
 
-    plant: u32,
-    meat: u32,
+                self.network.set_update_scheme(update);
+            }
+            if let &InputEvent::CycleLayout = e {
+                let layout = self.layout.next();
+                eprintln!("Layout: {:?}", layout);
 
-    alive: bool,
-}
+                self.set_layout(layout);
+            }
+            if let &InputEvent::Press(x, y) = e {
+                if let Some(idx) = self.network.pick(renderer.screen_to_world((x, y))) {
+                    let pinned = self.network.graph().node_payload(idx).unwrap().physics().pinned();
+
+                    // Held in place by the mouse, the rest keeps moving
+                    self.network.set_pinned(idx, true);
+                    self.dragging = Some((idx, pinned));
+                    self.selected = Some(idx);
+                } else {
+                    self.missed = Some((x, y));
+                }
+            }
+            if let &InputEvent::Hover(x, y) = e {
+                self.hovered = self.network.pick(renderer.screen_to_world((x, y)));
+            }
+            if let &InputEvent::Drag(x, y) = e {
+                if let Some((idx, _)) = self.dragging {
+                    self.network.move_agent(idx, renderer.screen_to_world((x, y)));
+                }
+            }
+            if let &InputEvent::Release(x, y) = e {
+                if let Some((idx, pinned)) = self.dragging.take() {
+                    self.network.set_pinned(idx, pinned);
+                }
+
+                // A click into empty space, not the end of moving the view
+                if self.missed.take() == Some((x, y)) {
+                    self.selected = None;
+                }
+            }
+            if let &InputEvent::TogglePin(x, y) = e {
+                if let Some(idx) = self.network.pick(renderer.screen_to_world((x, y))) {
+                    match self.dragging {
+                        Some((held, ref mut pinned)) if held == idx => *pinned = !*pinned,
+                        _ => {
+                            let pinned = self.network.graph().node_payload(idx).unwrap().physics().pinned();
+                            self.network.set_pinned(idx, !pinned);
+                        }
+                    }
+                }
+            }
+            if let &InputEvent::NextParam = e {
+                self.param = (self.param + 1) % PARAM_NAMES.len();
+
+                let name = PARAM_NAMES[self.param];
+                eprintln!("Selected: {} = {}", name, self.network.params().get(name).unwrap());
+            }
+            if let &InputEvent::ScaleParam(factor) = e {
+                let name = PARAM_NAMES[self.param];
+                let mut params = *self.network.params();
 
-impl AbstractComponent for LogicComponent {
-    fn new(rng: &mut rand::ThreadRng) -> Self {
-        let p = rng.gen::<f32>();
-        let ptype = if p < 0.5 {
-            ProductionType::Hunter
+                let value = params.get(name).unwrap() * factor;
+                match params.set(name, value) {
+                    Ok(()) => {
+                        eprintln!("{} = {}", name, value);
+                        self.network.set_params(params);
+                    }
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+            if let &InputEvent::Quit = e {
+                return true;
+            }
+            if let &InputEvent::Rebuild = e {
+                // Fresh network, but still reproducible from the initial seed
+                self.seed = self.seed.wrapping_add(1);
+                eprintln!("Seed: {}", self.seed);
+
+                let integrator = self.network.integrator();
+                let update = self.network.update_scheme();
+                let params = *self.network.params();
+                self.dragging = None;
+                self.selected = None;
+                self.hovered = None;
+                self.time = 0.0;
+                self.ticks = 0;
+
+                self.network = Network::generate(self.n, &self.topology, self.seed);
+                self.network.set_params(params);
+                self.network.set_integrator(integrator);
+                self.network.set_update_scheme(update);
+                self.network.physics_tick_till_rest(TIME_STEP, VEL_THRESH, IT_THRESH);
+                self.network.apply_layout(self.layout);
+            }
+        }
+
+        // Dragging an agent does not move the view
+        let events = if self.dragging.is_some() {
+            events.into_iter().filter(|e| match *e { InputEvent::Shift(..) => false, _ => true }).collect()
         } else {
-            ProductionType::Gatherer
+            events
         };
 
-        LogicComponent { ptype: ptype, plant: 10, meat: 10, alive: true }
+        renderer.apply_events(&events);
+        false
     }
 }
+
+fn update_look(_: NodeIndex, logic: &LogicComponent, physics: &mut PhysicsComponent) {
+    physics.color = if logic.ptype == ProductionType::Hunter {
+        (0.9, 0.08, 0.04)
+    } else {
+        (0.0, 0.6, 0.1)
+    };
+}
@@ -1,5 +1,14 @@
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 use clap::{Arg, App};
+use rand;
+use rand::{Rng, SeedableRng, StdRng};
+
+use std::fs::File;
+use std::io::Read;
+
+use network::{Topology, Integrator, UpdateScheme, PhysicsParams, Layout};
+use network::ADAPTIVE_TOL;
+use economy::EconomyParams;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Vec2 {
@@ -73,40 +82,86 @@ impl SubAssign for Vec2 {
 }
 
 
+// Fixed time step driven by the elapsed wall clock time
 pub struct Ticker {
-    // ticks since last fire
-    ticks: usize,
-
-    // every period-th tick should fire
-    period: usize,
+    // Seconds not yet covered by ticks
+    acc: f32,
 
-    // Fire on first tick always
-    first: bool
+    // Seconds between ticks
+    interval: f32,
 }
 
 impl Ticker {
-    pub fn new(period: usize) -> Ticker {
-        Ticker{ ticks: 0, period: period, first: true }
+    pub fn new(interval: f32) -> Ticker {
+        Ticker { acc: 0.0, interval: interval }
     }
 
-    pub fn tick(&mut self) -> bool {
-        if self.first {
-            self.first = false;
-            return true;
-        }
+    // Number of ticks due after dt more seconds. At most max ticks are
+    // returned and the rest is dropped, so a slow frame does not make the
+    // following frames even slower.
+    pub fn advance(&mut self, dt: f32, max: usize) -> usize {
+        self.acc += dt;
 
-        self.ticks += 1;
+        let due = (self.acc / self.interval) as usize;
+        self.acc -= due as f32 * self.interval;
 
-        if self.ticks >= self.period {
-            self.ticks -= self.period;
-            true
-        } else {
-            false
-        }
+        due.min(max)
     }
 }
 
-pub fn get_args() -> (usize, f32) {
+// Generator behind every random decision of a simulation
+pub type SimRng = StdRng;
+
+pub fn seeded_rng(seed: u64) -> SimRng {
+    let words = [(seed & 0xffff_ffff) as usize, (seed >> 32) as usize];
+    StdRng::from_seed(&words[..])
+}
+
+pub struct Args {
+    pub n: usize,
+    pub topology: Topology,
+    pub seed: u64,
+    pub params: PhysicsParams,
+    pub economy: EconomyParams,
+    pub integrator: Integrator,
+    pub update: UpdateScheme,
+    pub layout: Layout,
+
+    pub headless: bool,
+    pub steps: usize,
+    pub output: Option<String>,
+
+    // Images of the final state in headless mode
+    pub export: Vec<String>,
+    pub export_size: (u32, u32),
+}
+
+// Validator of counts that must not be zero
+fn at_least_one(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(0) => Err(String::from("must be at least 1")),
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("{} is not a whole number", value)),
+    }
+}
+
+// Image size given as WxH, None unless both are at least 1
+fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let mut split = text.splitn(2, 'x');
+    let mut dim = || split.next().and_then(|d| d.parse::<u32>().ok()).filter(|&d| d > 0);
+
+    Some((dim()?, dim()?))
+}
+
+// Validator of image sizes
+fn image_size(value: String) -> Result<(), String> {
+    match parse_size(&value) {
+        Some(_) => Ok(()),
+        None => Err(String::from("expected WxH with a width and height of at least 1")),
+    }
+}
+
+pub fn get_args() -> Args {
     let matches = App::new("Agent based simulation")
         .version("0.1.0")
         .author("Florian Marending")
@@ -118,12 +173,111 @@ pub fn get_args() -> (usize, f32) {
         .arg(Arg::with_name("d")
              .short("d")
              .long("neighbor_degree")
-             .help("Sets the neighborhood degree in the network")
+             .help("Sets the probability of a relation between two agents (within a block for sbm)")
+             .takes_value(true))
+        .arg(Arg::with_name("t")
+             .short("t")
+             .long("topology")
+             .help("Sets the network topology")
+             .possible_values(&["er", "ws", "ba", "sbm", "regular", "lattice", "torus", "complete", "star", "ring"])
+             .takes_value(true))
+        .arg(Arg::with_name("k")
+             .short("k")
+             .long("degree")
+             .help("Sets the number of neighbors for ws and regular, the number of attachments for ba")
+             .takes_value(true))
+        .arg(Arg::with_name("b")
+             .short("b")
+             .long("beta")
+             .help("Sets the rewiring probability for ws")
+             .takes_value(true))
+        .arg(Arg::with_name("blocks")
+             .long("blocks")
+             .help("Sets the number of blocks for sbm")
+             .takes_value(true)
+             .validator(at_least_one))
+        .arg(Arg::with_name("p_out")
+             .long("p_out")
+             .help("Sets the probability of a relation across blocks for sbm")
+             .takes_value(true))
+        .arg(Arg::with_name("seed")
+             .short("s")
+             .long("seed")
+             .help("Sets the random seed, runs with equal seed and parameters are identical")
+             .takes_value(true))
+        .arg(Arg::with_name("theta")
+             .long("theta")
+             .help("Sets the Barnes-Hut opening angle for repulsion, 0 computes it exactly")
+             .takes_value(true))
+        .arg(Arg::with_name("config")
+             .long("config")
+             .help("Reads physics parameters from a file with one name = value pair per line")
              .takes_value(true))
+        .arg(Arg::with_name("param")
+             .short("p")
+             .long("param")
+             .help("Sets a physics parameter as name=value, overrides the config file")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("economy")
+             .short("e")
+             .long("economy")
+             .help("Sets an economy parameter as name=value")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("integrator")
+             .long("integrator")
+             .help("Sets the integrator of the layout physics")
+             .possible_values(&["euler", "verlet", "rk4", "adaptive"])
+             .takes_value(true))
+        .arg(Arg::with_name("update")
+             .long("update")
+             .help("Sets the order agents run their rules in during a logic tick")
+             .possible_values(&["sync", "sequential", "continuous"])
+             .takes_value(true))
+        .arg(Arg::with_name("layout")
+             .long("layout")
+             .help("Sets the layout, all but force are static and deterministic")
+             .possible_values(&["force", "fr", "kk", "spectral", "circular", "shell"])
+             .takes_value(true))
+        .arg(Arg::with_name("headless")
+             .long("headless")
+             .help("Runs without a window and writes the final state"))
+        .arg(Arg::with_name("steps")
+             .long("steps")
+             .help("Sets the maximal number of logic ticks in headless mode")
+             .takes_value(true))
+        .arg(Arg::with_name("output")
+             .short("o")
+             .long("output")
+             .help("Sets the file the final state is written to in headless mode, defaults to stdout")
+             .takes_value(true))
+        .arg(Arg::with_name("export")
+             .long("export")
+             .value_name("FILE")
+             .help("Draws the final state in headless mode to an .svg or .png file, can be repeated")
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("export_size")
+             .long("export-size")
+             .value_name("WxH")
+             .help("Sets the size of exported images in pixels")
+             .takes_value(true)
+             .validator(image_size))
         .get_matches();
 
     let mut n = ::NUM_AGENTS;
     let mut p = ::DEGREE_P;
+    let mut k = ::DEGREE_K;
+    let mut beta = ::BETA;
+    let mut blocks = ::BLOCKS;
+    let mut p_out = ::P_OUT;
+    let mut steps = ::STEPS;
+    let mut export_size = ::EXPORT_SIZE;
+    let mut params = PhysicsParams::new();
+    let mut economy = EconomyParams::new();
 
     if let Some(x) = matches.value_of("n") {
         n = x.parse::<usize>().expect("Error: Not a number");
@@ -133,5 +287,141 @@ pub fn get_args() -> (usize, f32) {
         p = x.parse::<f32>().expect("Error: Not a number");
     }
 
-    (n, p)
+    if let Some(x) = matches.value_of("k") {
+        k = x.parse::<usize>().expect("Error: Not a number");
+    }
+
+    if let Some(x) = matches.value_of("b") {
+        beta = x.parse::<f32>().expect("Error: Not a number");
+    }
+
+    if let Some(x) = matches.value_of("blocks") {
+        blocks = x.parse::<usize>().expect("Error: Not a number");
+    }
+
+    if let Some(x) = matches.value_of("p_out") {
+        p_out = x.parse::<f32>().expect("Error: Not a number");
+    }
+
+    if let Some(x) = matches.value_of("steps") {
+        steps = x.parse::<usize>().expect("Error: Not a number");
+    }
+
+    if let Some(x) = matches.value_of("export_size") {
+        export_size = parse_size(x).expect("Error: Expected WxH");
+    }
+
+    if let Some(path) = matches.value_of("config") {
+        let mut text = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut text)).expect("Error: Could not read config file");
+
+        params.read_config(&text).expect("Error: Invalid config file");
+    }
+
+    if let Some(x) = matches.value_of("theta") {
+        params.theta = x.parse::<f32>().expect("Error: Not a number");
+    }
+
+    if let Some(pairs) = matches.values_of("param") {
+        for pair in pairs {
+            params.set_pair(pair).expect("Error: Invalid parameter");
+        }
+    }
+
+    if let Some(pairs) = matches.values_of("economy") {
+        for pair in pairs {
+            economy.set_pair(pair).expect("Error: Invalid economy parameter");
+        }
+    }
+
+    let seed = match matches.value_of("seed") {
+        Some(x) => x.parse::<u64>().expect("Error: Not a number"),
+        None => rand::thread_rng().gen::<u64>(),
+    };
+
+    let integrator = match matches.value_of("integrator").unwrap_or("euler") {
+        "verlet" => Integrator::VelocityVerlet,
+        "rk4" => Integrator::RungeKutta4,
+        "adaptive" => Integrator::adaptive(ADAPTIVE_TOL),
+        _ => Integrator::SemiImplicitEuler,
+    };
+
+    let update = match matches.value_of("update").unwrap_or("sync") {
+        "sequential" => UpdateScheme::RandomSequential,
+        "continuous" => UpdateScheme::Continuous,
+        _ => UpdateScheme::Synchronous,
+    };
+
+    let layout = match matches.value_of("layout").unwrap_or("force") {
+        "fr" => Layout::FruchtermanReingold,
+        "kk" => Layout::KamadaKawai,
+        "spectral" => Layout::Spectral,
+        "circular" => Layout::Circular,
+        "shell" => Layout::Shell,
+        _ => Layout::Force,
+    };
+
+    let topology = match matches.value_of("t").unwrap_or("er") {
+        "ws" => Topology::WattsStrogatz { k: k, beta: beta },
+        "ba" => Topology::BarabasiAlbert { m: k },
+        "sbm" => Topology::StochasticBlock { blocks: blocks, p_in: p, p_out: p_out },
+        "regular" => Topology::RandomRegular { d: k },
+        "lattice" => Topology::Lattice { periodic: false },
+        "torus" => Topology::Lattice { periodic: true },
+        "complete" => Topology::Complete,
+        "star" => Topology::Star,
+        "ring" => Topology::Ring,
+        _ => Topology::ErdosRenyi { p: p },
+    };
+
+    Args {
+        n: n,
+        topology: topology,
+        seed: seed,
+        params: params,
+        economy: economy,
+        integrator: integrator,
+        update: update,
+        layout: layout,
+        headless: matches.is_present("headless"),
+        steps: steps,
+        output: matches.value_of("output").map(|x| x.to_string()),
+        export: matches.values_of("export").map(|v| v.map(|x| x.to_string()).collect()).unwrap_or_else(Vec::new),
+        export_size: export_size,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use util::Ticker;
+    use super::{at_least_one, parse_size};
+
+    #[test]
+    fn ticker() {
+        let mut ticker = Ticker::new(0.5);
+
+        assert_eq!(ticker.advance(0.25, 10), 0);
+        assert_eq!(ticker.advance(0.5, 10), 1);
+        assert_eq!(ticker.advance(1.0, 10), 2);
+
+        // The remaining quarter second is kept, the backlog beyond max is not
+        assert_eq!(ticker.advance(10.0, 3), 3);
+        assert_eq!(ticker.advance(0.25, 3), 1);
+    }
+
+    #[test]
+    fn counts() {
+        assert!(at_least_one(String::from("2")).is_ok());
+        assert!(at_least_one(String::from("0")).is_err());
+        assert!(at_least_one(String::from("-1")).is_err());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("640x480"), Some((640, 480)));
+        assert_eq!(parse_size("0x480"), None);
+        assert_eq!(parse_size("640"), None);
+        assert_eq!(parse_size("640x"), None);
+    }
 }